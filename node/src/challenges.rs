@@ -0,0 +1,190 @@
+//! Answers the proof-of-storage challenges of the template pallet on behalf of the storage
+//! providers whose keys are in the local keystore.
+//!
+//! Storage provider keys are sr25519 keys of the `stor` key type, which can be added with
+//! `node-template key insert --key-type stor --scheme sr25519`. The keys are looked up when the
+//! node starts: nodes started without any of them do not answer challenges.
+
+use crate::{files::merkle_proof, service::FullClient};
+
+use futures::StreamExt;
+use node_template_runtime::{
+	self as runtime,
+	opaque::Block,
+	pallet_template,
+	AccountId, Index,
+};
+use pallet_template_rpc::TemplateRuntimeApi;
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use sc_transaction_pool_api::{TransactionPool, TransactionSource};
+use sp_api::ProvideRuntimeApi;
+use sp_core::{crypto::KeyTypeId, sr25519, Encode};
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+use sp_runtime::{
+	generic::{BlockId, Era},
+	OpaqueExtrinsic,
+};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+use substrate_frame_rpc_system::AccountNonceApi;
+
+const LOG_TARGET: &str = "storage-challenges";
+
+/// Key type of the storage provider keys.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"stor");
+
+type FullPool = sc_transaction_pool::FullPool<Block, FullClient>;
+
+/// Whether `keystore` holds any storage provider key.
+pub fn has_provider_keys(keystore: &SyncCryptoStorePtr) -> bool {
+	!SyncCryptoStore::sr25519_public_keys(&**keystore, KEY_TYPE).is_empty()
+}
+
+/// Answers the open challenges of the storage providers whose keys are in `keystore`, every
+/// time a new best block is imported.
+pub async fn answer_challenges(
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	keystore: SyncCryptoStorePtr,
+) {
+	let mut imports = client.import_notification_stream();
+	// challenges whose answer is in the pool, not included yet
+	let mut answered = HashSet::new();
+	let mut next_nonces = HashMap::<AccountId, Index>::new();
+
+	while let Some(notification) = imports.next().await {
+		if !notification.is_new_best {
+			continue
+		}
+		let at = BlockId::Hash(notification.hash);
+		for public in SyncCryptoStore::sr25519_public_keys(&*keystore, KEY_TYPE) {
+			let provider = AccountId::from(public);
+			let challenges = match client.runtime_api().get_challenges(&at, provider.clone()) {
+				Ok(challenges) => challenges,
+				Err(err) => {
+					log::warn!(target: LOG_TARGET, "Failed to get the challenges of {}: {:?}", provider, err);
+					continue
+				},
+			};
+			answered.retain(|(account, challenge_id)| {
+				account != &provider || challenges.iter().any(|(id, _, _)| id == challenge_id)
+			});
+
+			for (challenge_id, merkle_root, chunk_index) in challenges {
+				if answered.contains(&(provider.clone(), challenge_id)) {
+					continue
+				}
+				let proof = match merkle_proof(&client, &at, merkle_root, chunk_index) {
+					Some(proof) => proof,
+					None => {
+						log::warn!(
+							target: LOG_TARGET,
+							"Cannot answer challenge {} of {}: the chunk is not available",
+							challenge_id,
+							provider,
+						);
+						continue
+					},
+				};
+				let chain_nonce = match client.runtime_api().account_nonce(&at, provider.clone()) {
+					Ok(nonce) => nonce,
+					Err(err) => {
+						log::warn!(target: LOG_TARGET, "Failed to get the nonce of {}: {:?}", provider, err);
+						break
+					},
+				};
+				let nonce = next_nonces.get(&provider).map_or(chain_nonce, |next| chain_nonce.max(*next));
+				let (content, siblings) = match (proof.content.try_into(), proof.siblings.try_into()) {
+					(Ok(content), Ok(siblings)) => (content, siblings),
+					_ => {
+						log::warn!(
+							target: LOG_TARGET,
+							"Cannot answer challenge {} of {}: the proof exceeds the runtime bounds",
+							challenge_id,
+							provider,
+						);
+						continue
+					},
+				};
+				let call = runtime::RuntimeCall::TemplateModule(pallet_template::Call::respond_challenge {
+					challenge_id,
+					content,
+					siblings,
+				});
+				let extrinsic = match create_extrinsic(&client, &keystore, public, call, nonce) {
+					Some(extrinsic) => extrinsic,
+					None => {
+						log::warn!(target: LOG_TARGET, "Failed to sign the answer of challenge {}", challenge_id);
+						continue
+					},
+				};
+				match pool.submit_one(&at, TransactionSource::Local, extrinsic).await {
+					Ok(_) => {
+						log::info!(target: LOG_TARGET, "Answered challenge {} of {}", challenge_id, provider);
+						answered.insert((provider.clone(), challenge_id));
+						next_nonces.insert(provider.clone(), nonce + 1);
+					},
+					Err(err) => {
+						log::warn!(target: LOG_TARGET, "Failed to answer challenge {}: {:?}", challenge_id, err);
+						next_nonces.remove(&provider);
+					},
+				}
+			}
+		}
+	}
+}
+
+/// Signs `call` with the given keystore key. The extrinsic is immortal, since it is only valid
+/// until the challenge expires anyway.
+fn create_extrinsic(
+	client: &FullClient,
+	keystore: &SyncCryptoStorePtr,
+	public: sr25519::Public,
+	call: runtime::RuntimeCall,
+	nonce: Index,
+) -> Option<OpaqueExtrinsic> {
+	let genesis_hash = client.block_hash(0).ok().flatten()?;
+	let extra: runtime::SignedExtra = (
+		frame_system::CheckNonZeroSender::<runtime::Runtime>::new(),
+		frame_system::CheckSpecVersion::<runtime::Runtime>::new(),
+		frame_system::CheckTxVersion::<runtime::Runtime>::new(),
+		frame_system::CheckGenesis::<runtime::Runtime>::new(),
+		frame_system::CheckEra::<runtime::Runtime>::from(Era::Immortal),
+		frame_system::CheckNonce::<runtime::Runtime>::from(nonce),
+		frame_system::CheckWeight::<runtime::Runtime>::new(),
+		pallet_transaction_payment::ChargeTransactionPayment::<runtime::Runtime>::from(0),
+	);
+
+	let raw_payload = runtime::SignedPayload::from_raw(
+		call.clone(),
+		extra.clone(),
+		(
+			(),
+			runtime::VERSION.spec_version,
+			runtime::VERSION.transaction_version,
+			genesis_hash,
+			genesis_hash,
+			(),
+			(),
+			(),
+		),
+	);
+	let signature = raw_payload
+		.using_encoded(|payload| {
+			SyncCryptoStore::sign_with(&**keystore, KEY_TYPE, &public.into(), payload)
+		})
+		.ok()??;
+	let signature = sr25519::Signature::try_from(signature.as_slice()).ok()?;
+
+	Some(
+		runtime::UncheckedExtrinsic::new_signed(
+			call,
+			AccountId::from(public).into(),
+			runtime::Signature::Sr25519(signature),
+			extra,
+		)
+		.into(),
+	)
+}
@@ -7,6 +7,23 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	/// Keep the bodies of the finalized blocks, along with the files indexed in them with
+	/// `store_indexed`, whatever `--blocks-pruning` says.
+	#[arg(long)]
+	pub storage_chain: bool,
+
+	/// Maximum number of bytes `template_getFileBytes` returns at once.
+	#[arg(long, default_value_t = pallet_template_rpc::DEFAULT_MAX_FILE_BYTES)]
+	pub rpc_max_file_bytes: u32,
+
+	/// Serve the files of the template pallet over HTTP on the given port.
+	#[arg(long, value_name = "PORT")]
+	pub file_gateway_port: Option<u16>,
+
+	/// Listen to all network interfaces with the file gateway, instead of the local one only.
+	#[arg(long, requires = "file_gateway_port")]
+	pub file_gateway_external: bool,
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -9,6 +9,7 @@ use node_template_runtime::{Block, EXISTENTIAL_DEPOSIT};
 use sc_cli::{ChainSpec, RuntimeVersion, SubstrateCli};
 use sc_service::PartialComponents;
 use sp_keyring::Sr25519Keyring;
+use std::net::{Ipv4Addr, SocketAddr};
 
 impl SubstrateCli for Cli {
 	fn impl_name() -> String {
@@ -203,8 +204,15 @@ pub fn run() -> sc_cli::Result<()> {
 		},
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
+			let storage_chain = cli.storage_chain;
+			let max_file_bytes = cli.rpc_max_file_bytes;
+			let file_gateway = cli.file_gateway_port.map(|port| {
+				let ip = if cli.file_gateway_external { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+				SocketAddr::new(ip.into(), port)
+			});
 			runner.run_node_until_exit(|config| async move {
-				service::new_full(config).map_err(sc_cli::Error::Service)
+				service::new_full(config, storage_chain, max_file_bytes, file_gateway)
+					.map_err(sc_cli::Error::Service)
 			})
 		},
 	}
@@ -0,0 +1,31 @@
+//! Reads the files of the template pallet, for the services of the node that serve or prove them.
+
+use crate::service::FullClient;
+
+use node_template_runtime::{
+	opaque::Block,
+	pallet_template::{FileError, MerkleProof},
+};
+use pallet_template_rpc::TemplateRuntimeApi;
+use sc_client_api::BlockBackend;
+use sp_api::ProvideRuntimeApi;
+use sp_core::{Decode, H256};
+use sp_runtime::generic::BlockId;
+
+/// Merkle proof of a chunk, read from the state or rebuilt out of the indexed file bytes for
+/// the files stored with `store_indexed`.
+pub fn merkle_proof(
+	client: &FullClient,
+	at: &BlockId<Block>,
+	merkle_root: Vec<u8>,
+	chunk_index: u32,
+) -> Option<MerkleProof> {
+	let api = client.runtime_api();
+	match api.get_proof(at, merkle_root.clone(), chunk_index).ok()? {
+		Err(FileError::Indexed) => {},
+		result => return result.ok(),
+	}
+	let hash = H256::decode(&mut &merkle_root[..]).ok()?;
+	let file_bytes = client.indexed_transaction(hash).ok()??;
+	api.get_indexed_proof(at, merkle_root, file_bytes, chunk_index).ok()?.ok()
+}
@@ -0,0 +1,290 @@
+//! HTTP gateway serving the files of the template pallet, for browsers and tools that cannot
+//! easily speak JSON-RPC.
+//!
+//! - `GET /files/<merkle root>` returns the bytes of a file.
+//! - `GET /files/<merkle root>/chunks/<position>` returns the content of a chunk, along with its
+//!   merkle proof in the `X-Merkle-Proof` header.
+//!
+//! Both support single `Range` requests, and use the merkle root of the file as `ETag`. Files are
+//! read at the best block, on blocking threads since runtime calls may take a while. `HEAD`
+//! requests for files only read their info, not their bytes.
+
+use crate::{files::merkle_proof, service::FullClient};
+
+use hyper::{
+	header::{self, HeaderValue},
+	http::response::Builder,
+	service::{make_service_fn, service_fn},
+	Body, Method, Request, Response, Server, StatusCode,
+};
+use node_template_runtime::{opaque::Block, pallet_template::FileError};
+use pallet_template_rpc::TemplateRuntimeApi;
+use sc_client_api::BlockBackend;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{bytes::from_hex, hexdisplay::HexDisplay, Decode, H256};
+use sp_runtime::generic::BlockId;
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+const LOG_TARGET: &str = "file-gateway";
+
+/// Maximum number of bytes read from the runtime at once.
+const READ_WINDOW: u32 = 1024 * 1024;
+
+/// The gateway answers with a response in both cases, the error being an HTTP error response.
+type HttpResult = Result<Response<Body>, Response<Body>>;
+
+/// Serves the files of the template pallet over HTTP at `addr`, until the node stops.
+pub async fn run(client: Arc<FullClient>, addr: SocketAddr) {
+	let service = make_service_fn(move |_| {
+		let client = client.clone();
+		async move {
+			Ok::<_, Infallible>(service_fn(move |request| {
+				let client = client.clone();
+				async move {
+					let response = tokio::task::spawn_blocking(move || handle(&client, request))
+						.await
+						.unwrap_or_else(|err| {
+							log::error!(target: LOG_TARGET, "Failed to serve a request: {}", err);
+							error(StatusCode::INTERNAL_SERVER_ERROR, "Internal error")
+						});
+					Ok::<_, Infallible>(response)
+				}
+			}))
+		}
+	});
+
+	let server = match Server::try_bind(&addr) {
+		Ok(builder) => builder.serve(service),
+		Err(err) => {
+			log::error!(target: LOG_TARGET, "Failed to bind the file gateway to {}: {}", addr, err);
+			return
+		},
+	};
+	log::info!(target: LOG_TARGET, "File gateway listening on http://{}", addr);
+	if let Err(err) = server.await {
+		log::error!(target: LOG_TARGET, "File gateway stopped: {}", err);
+	}
+}
+
+fn handle(client: &FullClient, request: Request<Body>) -> Response<Body> {
+	if request.method() != Method::GET && request.method() != Method::HEAD {
+		return error(StatusCode::METHOD_NOT_ALLOWED, "Only GET and HEAD requests are supported")
+	}
+	let segments = request.uri().path().trim_matches('/').split('/').collect::<Vec<_>>();
+	let result = match segments.as_slice() {
+		["files", merkle_root] => serve_file(client, &request, merkle_root),
+		["files", merkle_root, "chunks", position] => match position.parse() {
+			Ok(position) => serve_chunk(client, &request, merkle_root, position),
+			Err(_) => Err(error(StatusCode::BAD_REQUEST, "Invalid chunk position")),
+		},
+		_ => Err(error(StatusCode::NOT_FOUND, "Not found")),
+	};
+	result.unwrap_or_else(|response| response)
+}
+
+fn serve_file(client: &FullClient, request: &Request<Body>, merkle_root: &str) -> HttpResult {
+	let merkle_root = parse_merkle_root(merkle_root)?;
+	let at = BlockId::Hash(client.info().best_hash);
+	let info = client
+		.runtime_api()
+		.get_file_info(&at, merkle_root.clone())
+		.map_err(runtime_error)?
+		.map_err(file_error)?;
+	let content_type = HeaderValue::from_bytes(&info.content_type)
+		.ok()
+		.filter(|content_type| !content_type.is_empty())
+		.unwrap_or(HeaderValue::from_static("application/octet-stream"));
+
+	let response = Response::builder().header(header::CONTENT_TYPE, content_type);
+	respond(request, &merkle_root, info.size, response, |start, end| {
+		read_file(client, &at, &merkle_root, start, end)
+	})
+}
+
+fn serve_chunk(
+	client: &FullClient,
+	request: &Request<Body>,
+	merkle_root: &str,
+	position: u32,
+) -> HttpResult {
+	let merkle_root = parse_merkle_root(merkle_root)?;
+	let at = BlockId::Hash(client.info().best_hash);
+	let proof = merkle_proof(client, &at, merkle_root.clone(), position)
+		.ok_or_else(|| error(StatusCode::NOT_FOUND, "Chunk not found"))?;
+	let siblings = proof
+		.siblings
+		.iter()
+		.map(|hash| HexDisplay::from(hash).to_string())
+		.collect::<Vec<_>>()
+		.join(",");
+
+	let response = Response::builder()
+		.header(header::CONTENT_TYPE, "application/octet-stream")
+		.header("X-Chunk-Position", proof.leaf_index)
+		.header("X-Chunk-Pieces", proof.pieces)
+		.header("X-Chunk-Size", proof.chunk_size)
+		.header("X-Tree-Mode", format!("{:?}", proof.mode).to_lowercase())
+		.header("X-Merkle-Proof", siblings);
+	let content = proof.content;
+	respond(request, &merkle_root, content.len() as u32, response, |start, end| {
+		Ok(content[start as usize..end as usize].to_vec())
+	})
+}
+
+/// Answers with the part of a resource of `size` bytes asked for by the `Range` header of the
+/// request, or with the whole resource. `read` reads the bytes of the resource between the given
+/// offsets, the end one excluded, and is not called for `HEAD` requests.
+fn respond(
+	request: &Request<Body>,
+	merkle_root: &[u8],
+	size: u32,
+	response: Builder,
+	read: impl FnOnce(u32, u32) -> Result<Vec<u8>, Response<Body>>,
+) -> HttpResult {
+	let etag = format!("\"{}\"", HexDisplay::from(&merkle_root));
+	let response = response.header(header::ETAG, &etag).header(header::ACCEPT_RANGES, "bytes");
+	if matches_etag(request, &etag) {
+		return build(response.status(StatusCode::NOT_MODIFIED), Vec::new())
+	}
+
+	let head = request.method() == Method::HEAD;
+	let content = |response: Builder, start: u32, end: u32| {
+		if head {
+			build(response.header(header::CONTENT_LENGTH, end - start), Vec::new())
+		} else {
+			build(response, read(start, end)?)
+		}
+	};
+	match byte_range(request, size) {
+		Some(Ok((start, end))) => content(
+			response
+				.status(StatusCode::PARTIAL_CONTENT)
+				.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end - 1, size)),
+			start,
+			end,
+		),
+		Some(Err(())) => build(
+			response
+				.status(StatusCode::RANGE_NOT_SATISFIABLE)
+				.header(header::CONTENT_RANGE, format!("bytes */{}", size)),
+			Vec::new(),
+		),
+		None => content(response.status(StatusCode::OK), 0, size),
+	}
+}
+
+/// Reads the bytes of a file between the given offsets, the end one excluded. The bytes of the
+/// files stored with `store_indexed` are read from the indexed block body instead of the state,
+/// and checked against their merkle root.
+fn read_file(
+	client: &FullClient,
+	at: &BlockId<Block>,
+	merkle_root: &[u8],
+	start: u32,
+	end: u32,
+) -> Result<Vec<u8>, Response<Body>> {
+	let api = client.runtime_api();
+	let mut bytes = Vec::with_capacity((end - start) as usize);
+	while start + (bytes.len() as u32) < end {
+		let offset = start + bytes.len() as u32;
+		let length = (end - offset).min(READ_WINDOW);
+		match api.get_file_bytes(at, merkle_root.to_vec(), offset, length).map_err(runtime_error)? {
+			Ok(window) if !window.is_empty() => bytes.extend(window),
+			Ok(_) => return Err(error(StatusCode::INTERNAL_SERVER_ERROR, "File shorter than expected")),
+			Err(FileError::Indexed) => {
+				let hash = H256::decode(&mut &merkle_root[..]).map_err(runtime_error)?;
+				let file_bytes = client
+					.indexed_transaction(hash)
+					.map_err(runtime_error)?
+					.ok_or_else(|| error(StatusCode::NOT_FOUND, "File bytes not available"))?;
+				// the indexed bytes are checked against the merkle root before being served
+				return api
+					.get_indexed_file_bytes(at, merkle_root.to_vec(), file_bytes, start, end - start)
+					.map_err(runtime_error)?
+					.map_err(file_error)
+			},
+			Err(err) => return Err(file_error(err)),
+		}
+	}
+	Ok(bytes)
+}
+
+/// Offsets of the single range of bytes the `Range` header of the request asks for, the end one
+/// excluded, or `Err` if it cannot be satisfied. Requests without range, with several ranges or
+/// with an invalid one get the whole resource, as if they had no `Range` header.
+fn byte_range(request: &Request<Body>, size: u32) -> Option<Result<(u32, u32), ()>> {
+	let range = request.headers().get(header::RANGE)?.to_str().ok()?.strip_prefix("bytes=")?;
+	if range.contains(',') {
+		return None
+	}
+	let (first, last) = range.trim().split_once('-')?;
+	if first.is_empty() {
+		// suffix range, the last bytes of the resource
+		let length = last.parse::<u32>().ok()?;
+		if length == 0 || size == 0 {
+			return Some(Err(()))
+		}
+		return Some(Ok((size.saturating_sub(length), size)))
+	}
+	let start = first.parse::<u32>().ok()?;
+	let end = match last {
+		"" => size,
+		last => last.parse::<u32>().ok()?.saturating_add(1).min(size),
+	};
+	if end <= start && start < size {
+		// the last byte comes before the first one
+		return None
+	}
+	if start >= size {
+		return Some(Err(()))
+	}
+	Some(Ok((start, end)))
+}
+
+/// Whether the `If-None-Match` header of the request matches the given entity tag.
+fn matches_etag(request: &Request<Body>, etag: &str) -> bool {
+	request
+		.headers()
+		.get(header::IF_NONE_MATCH)
+		.and_then(|value| value.to_str().ok())
+		.map_or(false, |tags| tags.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"))
+}
+
+fn parse_merkle_root(merkle_root: &str) -> Result<Vec<u8>, Response<Body>> {
+	from_hex(merkle_root)
+		.ok()
+		.filter(|merkle_root| merkle_root.len() == H256::len_bytes())
+		.ok_or_else(|| error(StatusCode::BAD_REQUEST, "Invalid merkle root"))
+}
+
+fn build(response: Builder, body: Vec<u8>) -> HttpResult {
+	response
+		.body(Body::from(body))
+		.map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()))
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+	let mut response = Response::new(Body::from(format!("{}\n", message)));
+	*response.status_mut() = status;
+	response
+}
+
+fn file_error(err: FileError) -> Response<Body> {
+	match err {
+		FileError::InvalidMerkleRoot => error(StatusCode::BAD_REQUEST, "Invalid merkle root"),
+		FileError::FileNotFound => error(StatusCode::NOT_FOUND, "File not found"),
+		FileError::PositionOutOfRange { .. } |
+		FileError::OffsetOutOfRange { .. } |
+		FileError::NoPositions => error(StatusCode::RANGE_NOT_SATISFIABLE, "Out of the file"),
+		FileError::Indexed |
+		FileError::NotIndexed |
+		FileError::BytesMismatch |
+		FileError::IncompleteFile => error(StatusCode::NOT_FOUND, "File bytes not available"),
+	}
+}
+
+fn runtime_error(err: impl std::fmt::Debug) -> Response<Body> {
+	log::warn!(target: LOG_TARGET, "Failed to read from the runtime: {:?}", err);
+	error(StatusCode::INTERNAL_SERVER_ERROR, "Runtime error")
+}
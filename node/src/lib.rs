@@ -1,3 +1,6 @@
 pub mod chain_spec;
+pub mod challenges;
+pub mod files;
+pub mod gateway;
 pub mod rpc;
 pub mod service;
@@ -5,8 +5,11 @@ mod chain_spec;
 #[macro_use]
 mod service;
 mod benchmarking;
+mod challenges;
 mod cli;
 mod command;
+mod files;
+mod gateway;
 mod rpc;
 
 fn main() -> sc_cli::Result<()> {
@@ -6,10 +6,10 @@ use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_finality_grandpa::SharedVoterState;
 use sc_keystore::LocalKeystore;
-use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
+use sc_service::{error::Error as ServiceError, BlocksPruning, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
-use std::{sync::Arc, time::Duration};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -155,7 +155,22 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+///
+/// With `storage_chain`, the node keeps the indexed transactions of the finalized blocks, so
+/// that it can serve the files stored with `store_indexed`. `max_file_bytes` limits the size of
+/// the `template_getFileBytes` responses. With `file_gateway`, the files are also served over
+/// HTTP at the given address.
+pub fn new_full(
+	mut config: Configuration,
+	storage_chain: bool,
+	max_file_bytes: u32,
+	file_gateway: Option<SocketAddr>,
+) -> Result<TaskManager, ServiceError> {
+	if storage_chain {
+		// indexed transactions are pruned along with the block bodies
+		config.blocks_pruning = BlocksPruning::KeepFinalized;
+	}
+
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -223,9 +238,14 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		let client = client.clone();
 		let pool = transaction_pool.clone();
 
-		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+		Box::new(move |deny_unsafe, subscription_executor| {
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				max_file_bytes,
+				subscription_executor,
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -244,6 +264,27 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		telemetry: telemetry.as_mut(),
 	})?;
 
+	// only the nodes holding a storage provider key have challenges to answer
+	if crate::challenges::has_provider_keys(&keystore_container.sync_keystore()) {
+		task_manager.spawn_handle().spawn(
+			"storage-challenges",
+			None,
+			crate::challenges::answer_challenges(
+				client.clone(),
+				transaction_pool.clone(),
+				keystore_container.sync_keystore(),
+			),
+		);
+	}
+
+	if let Some(addr) = file_gateway {
+		task_manager.spawn_handle().spawn(
+			"file-gateway",
+			None,
+			crate::gateway::run(client.clone(), addr),
+		);
+	}
+
 	if role.is_authority() {
 		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
@@ -0,0 +1,398 @@
+//! # File Merkle Proof
+//!
+//! Typed merkle proofs of the file chunks served by the template pallet, along with the logic
+//! needed to verify them against a merkle root.
+//!
+//! This crate is `no_std` and has very few dependencies, so that the runtime, the RPC layer and
+//! light clients can all share the very same verification code. It also holds the other types
+//! the pallet exposes through its runtime API.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_std::{vec, vec::Vec};
+
+/// Files are divided in chunks of `2^n` bytes, `n` being at most this exponent.
+pub const MAX_CHUNK_SIZE_EXPONENT: u8 = 24;
+/// Length of the hashes of the merkle tree, in bytes.
+pub const HASH_SIZE: usize = 32;
+
+/// A node of the merkle tree.
+pub type Hash = [u8; HASH_SIZE];
+
+/// Hashing algorithm used to build the merkle tree of a file, both its leaves and its nodes.
+pub trait FileHasher {
+    /// Hashes the given data.
+    fn hash(data: &[u8]) -> Hash;
+}
+
+/// The `sha256` hashing algorithm, as used by the Bittorrent v2 protocol.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Sha2_256;
+
+impl FileHasher for Sha2_256 {
+    fn hash(data: &[u8]) -> Hash {
+        sp_core_hashing::sha2_256(data)
+    }
+}
+
+/// The `blake2b` hashing algorithm with a 256 bits output, as used by the substrate runtime.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Blake2_256;
+
+impl FileHasher for Blake2_256 {
+    fn hash(data: &[u8]) -> Hash {
+        sp_core_hashing::blake2_256(data)
+    }
+}
+
+/// The `keccak256` hashing algorithm, as used by the EVM.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Keccak256;
+
+impl FileHasher for Keccak256 {
+    fn hash(data: &[u8]) -> Hash {
+        sp_core_hashing::keccak_256(data)
+    }
+}
+
+/// Size of the blocks the merkle tree leaves are made of, as defined by BEP 52.
+pub const BEP52_BLOCK_SIZE: u32 = 16 * 1024;
+
+/// Rules to build the merkle tree of a file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Encode, Decode, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum TreeMode {
+    /// Chunks of any configured size, hashed with the configured `FileHasher`. The last chunk is
+    /// zero-filled up to the chunk size before being hashed.
+    #[default]
+    Standard,
+    /// Bittorrent v2 compatible tree, as described in
+    /// [BEP 52](https://www.bittorrent.org/beps/bep_0052.html): 16KiB blocks hashed with `sha256`,
+    /// where the last block is hashed as is. The merkle root is the `pieces root` of the file.
+    Bep52,
+}
+
+impl TreeMode {
+    /// Hashes a file chunk into a merkle tree leaf.
+    pub fn hash_leaf<H: FileHasher>(&self, chunk: &[u8], chunk_size: u32) -> Hash {
+        match self {
+            TreeMode::Standard if chunk.len() < chunk_size as usize => {
+                let mut padded = vec![0u8; chunk_size as usize];
+                padded[..chunk.len()].copy_from_slice(chunk);
+                H::hash(&padded)
+            },
+            TreeMode::Standard => H::hash(chunk),
+            TreeMode::Bep52 => Sha2_256::hash(chunk),
+        }
+    }
+
+    /// Hashes two sibling nodes into their parent node.
+    pub fn hash_node<H: FileHasher>(&self, left: &[u8], right: &[u8]) -> Hash {
+        let mut node = [0u8; HASH_SIZE * 2];
+        node[..HASH_SIZE].copy_from_slice(left);
+        node[HASH_SIZE..].copy_from_slice(right);
+        match self {
+            TreeMode::Standard => H::hash(&node),
+            TreeMode::Bep52 => Sha2_256::hash(&node),
+        }
+    }
+}
+
+/// Proves that a chunk belongs to a file, identified by its merkle root.
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct MerkleProof {
+    /// Position of the chunk within the file.
+    pub leaf_index: u32,
+    /// Total number of chunks of the file.
+    pub pieces: u32,
+    /// Size of the chunks the file is divided in. It is always a power of two.
+    pub chunk_size: u32,
+    /// Rules the merkle tree of the file was built with.
+    pub mode: TreeMode,
+    /// Sibling hashes, ordered from the leaf level up to the root.
+    pub siblings: Vec<Hash>,
+    /// Raw chunk content. The last chunk of a file may be shorter than `chunk_size`.
+    pub content: Vec<u8>,
+}
+
+impl MerkleProof {
+    /// Number of sibling hashes a proof must have for a file of `pieces` chunks.
+    pub fn depth(pieces: u32) -> usize {
+        pieces.next_power_of_two().trailing_zeros() as usize
+    }
+
+    /// Verifies that the chunk content belongs to the file whose merkle root is `merkle_root`.
+    /// `H` must be the same hashing algorithm the merkle tree was built with. It is ignored in
+    /// `TreeMode::Bep52`, which always uses `sha256`.
+    ///
+    /// The content is hashed into a leaf, following the rules of the tree mode. Then it is hashed
+    /// along with each sibling until the root is reached. At every level, the parity of the current
+    /// position tells whether the sibling is the left or the right node.
+    pub fn verify<H: FileHasher>(&self, merkle_root: &[u8]) -> bool {
+        if self.leaf_index >= self.pieces ||
+            self.siblings.len() != Self::depth(self.pieces) ||
+            !self.chunk_size.is_power_of_two() ||
+            self.chunk_size > 1 << MAX_CHUNK_SIZE_EXPONENT ||
+            self.content.len() > self.chunk_size as usize ||
+            (self.mode == TreeMode::Bep52 && self.chunk_size != BEP52_BLOCK_SIZE)
+        {
+            return false
+        }
+        let mut hash = self.mode.hash_leaf::<H>(&self.content, self.chunk_size);
+        let mut position = self.leaf_index;
+        for sibling in self.siblings.iter() {
+            hash = if position.is_multiple_of(2) {
+                self.mode.hash_node::<H>(&hash, sibling)
+            } else {
+                self.mode.hash_node::<H>(sibling, &hash)
+            };
+            position /= 2;
+        }
+        hash.as_slice() == merkle_root
+    }
+}
+
+/// Proves that several chunks belong to a file at once, identified by its merkle root.
+///
+/// Adjacent chunks share most of their sibling hashes, so instead of one `MerkleProof` per
+/// chunk, the proof holds every node needed to rebuild the root just once. Nodes that can be
+/// computed out of the chunks themselves are left out.
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct MultiProof {
+    /// Positions of the chunks within the file, in ascending order and without duplicates.
+    pub leaf_indices: Vec<u32>,
+    /// Total number of chunks of the file.
+    pub pieces: u32,
+    /// Size of the chunks the file is divided in. It is always a power of two.
+    pub chunk_size: u32,
+    /// Rules the merkle tree of the file was built with.
+    pub mode: TreeMode,
+    /// Hashes of the nodes that cannot be computed out of the chunks, ordered level by level
+    /// from the leaves up to the root, and from left to right within every level.
+    pub hashes: Vec<Hash>,
+    /// Raw content of the chunks, in the order of `leaf_indices`.
+    pub contents: Vec<Vec<u8>>,
+}
+
+impl MultiProof {
+    /// Verifies that the chunk contents belong to the file whose merkle root is `merkle_root`.
+    /// `H` must be the same hashing algorithm the merkle tree was built with. It is ignored in
+    /// `TreeMode::Bep52`, which always uses `sha256`.
+    ///
+    /// The contents are hashed into leaves, and the tree is rebuilt level by level: every known
+    /// node is hashed along with its sibling, which is either the next known node or the next
+    /// hash of the proof. All the hashes of the proof must be used by the time the root is reached.
+    pub fn verify<H: FileHasher>(&self, merkle_root: &[u8]) -> bool {
+        if self.leaf_indices.is_empty() ||
+            self.leaf_indices.len() != self.contents.len() ||
+            self.leaf_indices.windows(2).any(|pair| pair[0] >= pair[1]) ||
+            self.leaf_indices[self.leaf_indices.len() - 1] >= self.pieces ||
+            !self.chunk_size.is_power_of_two() ||
+            self.chunk_size > 1 << MAX_CHUNK_SIZE_EXPONENT ||
+            self.contents.iter().any(|content| content.len() > self.chunk_size as usize) ||
+            (self.mode == TreeMode::Bep52 && self.chunk_size != BEP52_BLOCK_SIZE)
+        {
+            return false
+        }
+        let mut level = self
+            .leaf_indices
+            .iter()
+            .zip(self.contents.iter())
+            .map(|(position, content)| {
+                (*position, self.mode.hash_leaf::<H>(content, self.chunk_size))
+            })
+            .collect::<Vec<(u32, Hash)>>();
+        let mut hashes = self.hashes.iter();
+        for _ in 0..MerkleProof::depth(self.pieces) {
+            let mut parents = Vec::with_capacity(level.len());
+            let mut i = 0;
+            while i < level.len() {
+                let (position, hash) = level[i];
+                let sibling = match level.get(i + 1) {
+                    Some((next, next_hash))
+                        if position.is_multiple_of(2) && *next == position + 1 =>
+                    {
+                        i += 1;
+                        *next_hash
+                    },
+                    _ => match hashes.next() {
+                        Some(sibling) => *sibling,
+                        None => return false,
+                    },
+                };
+                let parent = if position.is_multiple_of(2) {
+                    self.mode.hash_node::<H>(&hash, &sibling)
+                } else {
+                    self.mode.hash_node::<H>(&sibling, &hash)
+                };
+                parents.push((position / 2, parent));
+                i += 1;
+            }
+            level = parents;
+        }
+        hashes.next().is_none() && level[0].1.as_slice() == merkle_root
+    }
+}
+
+/// Why the runtime could not serve a request about a file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum FileError {
+    /// The merkle root is not a hash: it does not have the right length.
+    InvalidMerkleRoot,
+    /// No file is stored under the merkle root.
+    FileNotFound,
+    /// The file has no chunk at the given position.
+    PositionOutOfRange { position: u32, pieces: u32 },
+    /// The offset is past the end of the file.
+    OffsetOutOfRange { offset: u32, size: u32 },
+    /// No chunk position was given.
+    NoPositions,
+    /// The file was stored with `store_indexed`, so its bytes are not in the state. They must be
+    /// read from the indexed block body.
+    Indexed,
+    /// The file was not stored with `store_indexed`.
+    NotIndexed,
+    /// The given file bytes do not match the merkle root.
+    BytesMismatch,
+    /// Some chunk or merkle tree node of the file is missing from the state.
+    IncompleteFile,
+}
+
+/// A file uploaded in a block, as reported by its `FileUploaded` event.
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct UploadedFile<AccountId> {
+    pub merkle_root: Vec<u8>,
+    /// Account that uploaded the file.
+    pub owner: AccountId,
+    /// Exact size of the file, in bytes.
+    pub size: u32,
+    /// Number of chunks of the file.
+    pub pieces: u32,
+    /// Whether the file was already stored, the uploader becoming one of its co-owners.
+    pub shared: bool,
+}
+
+/// Human readable information about a stored file.
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct FileInfo<BlockNumber> {
+    /// Name of the file, empty until set by its owner.
+    pub name: Vec<u8>,
+    /// MIME type of the file, empty until set by its owner.
+    pub content_type: Vec<u8>,
+    /// Exact size of the file, in bytes.
+    pub size: u32,
+    /// Block the file was uploaded at.
+    pub uploaded_at: BlockNumber,
+    pub description: Option<Vec<u8>>,
+    /// Block at which the file expires, if it was uploaded with a retention period.
+    pub expires_at: Option<BlockNumber>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core_hashing::sha2_256;
+
+    /// Parent of two nodes, hashed without going through `TreeMode`.
+    fn parent(left: &Hash, right: &Hash) -> Hash {
+        sha2_256(&[&left[..], &right[..]].concat())
+    }
+
+    fn proof(leaf_index: u32, pieces: u32, siblings: Vec<Hash>, content: &[u8]) -> MerkleProof {
+        MerkleProof {
+            leaf_index,
+            pieces,
+            chunk_size: 4,
+            mode: TreeMode::Standard,
+            siblings,
+            content: content.to_vec(),
+        }
+    }
+
+    #[test]
+    fn siblings_are_placed_by_position_parity() {
+        let chunks = [*b"aaaa", *b"bbbb", *b"cccc", *b"dddd"];
+        let leaves = chunks.map(|chunk| sha2_256(&chunk));
+        let (left, right) = (parent(&leaves[0], &leaves[1]), parent(&leaves[2], &leaves[3]));
+        let root = parent(&left, &right);
+
+        // even positions have their sibling on the right, odd ones on the left
+        assert!(proof(0, 4, vec![leaves[1], right], b"aaaa").verify::<Sha2_256>(&root));
+        assert!(proof(1, 4, vec![leaves[0], right], b"bbbb").verify::<Sha2_256>(&root));
+        assert!(proof(2, 4, vec![leaves[3], left], b"cccc").verify::<Sha2_256>(&root));
+        assert!(proof(3, 4, vec![leaves[2], left], b"dddd").verify::<Sha2_256>(&root));
+        // the same hashes in the wrong order or at the wrong position do not reach the root
+        assert!(!proof(1, 4, vec![leaves[1], right], b"aaaa").verify::<Sha2_256>(&root));
+        assert!(!proof(0, 4, vec![right, leaves[1]], b"aaaa").verify::<Sha2_256>(&root));
+        assert!(!proof(3, 4, vec![leaves[3], left], b"cccc").verify::<Sha2_256>(&root));
+    }
+
+    #[test]
+    fn missing_leaves_and_short_chunks_are_padded_with_zeros() {
+        // the last chunk is zero-filled, and the fourth leaf is a zero hash
+        let leaves = [sha2_256(b"aaaa"), sha2_256(b"bbbb"), sha2_256(b"cc\0\0"), [0u8; HASH_SIZE]];
+        let root = parent(&parent(&leaves[0], &leaves[1]), &parent(&leaves[2], &leaves[3]));
+
+        assert!(proof(2, 3, vec![[0u8; HASH_SIZE], parent(&leaves[0], &leaves[1])], b"cc").verify::<Sha2_256>(&root));
+        assert!(proof(0, 3, vec![leaves[1], parent(&leaves[2], &leaves[3])], b"aaaa").verify::<Sha2_256>(&root));
+        // the padding leaf is not a chunk of the file
+        assert!(!proof(3, 3, vec![leaves[2], parent(&leaves[0], &leaves[1])], b"").verify::<Sha2_256>(&root));
+
+        let multi_proof = MultiProof {
+            leaf_indices: vec![1, 2],
+            pieces: 3,
+            chunk_size: 4,
+            mode: TreeMode::Standard,
+            hashes: vec![leaves[0], [0u8; HASH_SIZE]],
+            contents: vec![b"bbbb".to_vec(), b"cc".to_vec()],
+        };
+        assert!(multi_proof.verify::<Sha2_256>(&root));
+        assert!(!MultiProof { hashes: vec![[0u8; HASH_SIZE], leaves[0]], ..multi_proof }.verify::<Sha2_256>(&root));
+    }
+
+    #[test]
+    fn single_piece_files_have_no_siblings() {
+        let root = sha2_256(b"ab\0\0");
+        assert!(proof(0, 1, Vec::new(), b"ab").verify::<Sha2_256>(&root));
+        assert!(!proof(0, 1, vec![root], b"ab").verify::<Sha2_256>(&root));
+    }
+
+    #[test]
+    fn bep52_blocks_are_hashed_with_sha256_without_padding() {
+        let block = vec![7u8; BEP52_BLOCK_SIZE as usize];
+        let last = vec![9u8; 100];
+        let leaves = [sha2_256(&block), sha2_256(&block), sha2_256(&last), [0u8; HASH_SIZE]];
+        let root = parent(&parent(&leaves[0], &leaves[1]), &parent(&leaves[2], &leaves[3]));
+        let bep52_proof = MerkleProof {
+            leaf_index: 2,
+            pieces: 3,
+            chunk_size: BEP52_BLOCK_SIZE,
+            mode: TreeMode::Bep52,
+            siblings: vec![leaves[3], parent(&leaves[0], &leaves[1])],
+            content: last,
+        };
+
+        // the configured hasher is ignored
+        assert!(bep52_proof.verify::<Sha2_256>(&root));
+        assert!(bep52_proof.verify::<Blake2_256>(&root));
+        // BEP 52 blocks are always 16KiB
+        assert!(!MerkleProof { chunk_size: 4, ..bep52_proof.clone() }.verify::<Sha2_256>(&root));
+        // the same tree with a zero-filled last block is a standard one
+        assert!(!MerkleProof { mode: TreeMode::Standard, ..bep52_proof }.verify::<Sha2_256>(&root));
+    }
+}
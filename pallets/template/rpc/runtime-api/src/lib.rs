@@ -1,11 +1,40 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::Codec;
 use sp_std::vec::Vec;
 
+pub use pallet_template_merkle_proof::{FileError, FileInfo, Hash, MerkleProof, MultiProof, TreeMode, UploadedFile};
+
 sp_api::decl_runtime_apis! {
-    #[api_version(1)]
-    pub trait TemplateApi {
+    /// The version 1 only had `get_files` and `get_proof`, for files of 1KB chunks in the standard
+    /// mode. Their former signatures are kept so that clients can still call runtimes of that
+    /// version, as `get_files_before_version_2` and `get_proof_before_version_2`.
+    #[api_version(2)]
+    pub trait TemplateApi<AccountId, BlockNumber> where AccountId: Codec, BlockNumber: Codec {
+        #[changed_in(2)]
         fn get_files() -> Vec<(Vec<u8>, u32)>;
+        fn get_files() -> Vec<(Vec<u8>, u32, u32, TreeMode)>;
+        fn get_files_paged(start_after: Option<Hash>, limit: u32) -> (Vec<(Vec<u8>, u32, u32, TreeMode)>, Option<Hash>);
+        fn get_files_by_owner(owner: AccountId) -> Vec<(Vec<u8>, u32, u32, TreeMode)>;
+        fn get_file_info(merkle_root: Vec<u8>) -> Result<FileInfo<BlockNumber>, FileError>;
+        #[changed_in(2)]
         fn get_proof(merkle_root: Vec<u8>, position: u32) -> Option<(Vec<u8>, Vec<Vec<u8>>)>;
+        fn get_proof(merkle_root: Vec<u8>, position: u32) -> Result<MerkleProof, FileError>;
+        fn get_indexed_proof(merkle_root: Vec<u8>, file_bytes: Vec<u8>, position: u32) -> Result<MerkleProof, FileError>;
+        fn get_proofs(merkle_root: Vec<u8>, positions: Vec<u32>) -> Result<MultiProof, FileError>;
+        fn get_indexed_proofs(merkle_root: Vec<u8>, file_bytes: Vec<u8>, positions: Vec<u32>) -> Result<MultiProof, FileError>;
+        fn get_file_bytes(merkle_root: Vec<u8>, offset: u32, length: u32) -> Result<Vec<u8>, FileError>;
+        fn get_indexed_file_bytes(merkle_root: Vec<u8>, file_bytes: Vec<u8>, offset: u32, length: u32) -> Result<Vec<u8>, FileError>;
+        fn get_uploaded_files() -> Vec<UploadedFile<AccountId>>;
+        fn get_challenges(provider: AccountId) -> Vec<(u64, Vec<u8>, u32)>;
+        fn verify_chunk(
+            merkle_root: Vec<u8>,
+            position: u32,
+            content: Vec<u8>,
+            proof: Vec<Hash>,
+            pieces: u32,
+            chunk_size: u32,
+            mode: TreeMode,
+        ) -> bool;
     }
 }
\ No newline at end of file
@@ -1,90 +1,654 @@
 use std::sync::Arc;
 
+use futures::{FutureExt, StreamExt};
 use jsonrpsee::{
     core::{Error as JsonRpseeError, RpcResult},
     proc_macros::rpc,
-    types::error::{CallError, ErrorObject},
+    types::{error::{CallError, ErrorObject}, SubscriptionResult},
+    SubscriptionSink,
 };
-use sp_api::ProvideRuntimeApi;
+use codec::{Codec, Decode};
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
+use sp_core::{crypto::Ss58Codec, traits::SpawnNamed};
 use sp_runtime::generic::BlockId;
-use sp_runtime::traits::Block as BlockT;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
 use array_bytes;
+use serde_json::json;
 
 pub use pallet_template_runtime_api::TemplateApi as TemplateRuntimeApi;
+use pallet_template_runtime_api::{
+    FileError, FileInfo as RuntimeFileInfo, Hash, MerkleProof as ChunkProof, MultiProof as ChunksProof, TreeMode, UploadedFile,
+};
+
+/// Number of files returned by every page of `template_getFilesPaged` when no limit is given.
+const DEFAULT_PAGE_SIZE: u32 = 100;
+/// Maximum number of bytes `template_getFileBytes` returns at once, unless configured otherwise.
+pub const DEFAULT_MAX_FILE_BYTES: u32 = 4 * 1024 * 1024;
+/// Size of the chunks of the files of runtimes with the version 1 of the runtime API, which were
+/// all built in the standard mode.
+const LEGACY_CHUNK_SIZE: u32 = 1024;
+/// Target of the logs of the RPC methods.
+const LOG_TARGET: &str = "template-rpc";
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct HashItem {
     hash: String,
     pieces: u32,
+    chunk_size: u32,
+    mode: TreeMode,
+    /// Bittorrent v2 `pieces root` of the file, only available for files uploaded in BEP 52 mode.
+    pieces_root: Option<String>,
+}
+
+impl From<(Vec<u8>, u32, u32, TreeMode)> for HashItem {
+    fn from((hash, pieces, chunk_size, mode): (Vec<u8>, u32, u32, TreeMode)) -> Self {
+        HashItem {
+            pieces,
+            chunk_size,
+            mode,
+            pieces_root: (mode == TreeMode::Bep52).then(|| vec_to_hex_string(&hash)),
+            hash: vec_to_hex_string(&hash),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct FilesPage {
+    files: Vec<HashItem>,
+    /// Hash to pass as `start_after` in order to get the next page, if there are more files.
+    next_cursor: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct FileInfo<BlockNumber> {
+    hash: String,
+    /// Name of the file, empty until set by its owner.
+    name: String,
+    /// MIME type of the file, empty until set by its owner.
+    content_type: String,
+    /// Exact size of the file, in bytes.
+    size: u32,
+    uploaded_at: BlockNumber,
+    description: Option<String>,
+    /// Block at which the file expires, if it was uploaded with a retention period.
+    expires_at: Option<BlockNumber>,
+}
+
+impl<BlockNumber> FileInfo<BlockNumber> {
+    fn new(hash: String, info: RuntimeFileInfo<BlockNumber>) -> Self {
+        FileInfo {
+            hash,
+            name: String::from_utf8_lossy(&info.name).into_owned(),
+            content_type: String::from_utf8_lossy(&info.content_type).into_owned(),
+            size: info.size,
+            uploaded_at: info.uploaded_at,
+            description: info.description
+                .map(|description| String::from_utf8_lossy(&description).into_owned()),
+            expires_at: info.expires_at,
+        }
+    }
+}
+
+/// Blocks whose uploads are notified by `template_subscribeFiles`.
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionMode {
+    /// Every imported block, as soon as it is imported, forks included.
+    Imported,
+    /// Finalized blocks only, in order.
+    #[default]
+    Finalized,
+}
+
+/// A file uploaded in a block, as notified by `template_subscribeFiles`.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct FileUpload<BlockHash> {
+    hash: String,
+    /// Account that uploaded the file.
+    owner: String,
+    /// Exact size of the file, in bytes.
+    size: u32,
+    pieces: u32,
+    /// Whether the file was already stored, the uploader becoming one of its co-owners.
+    shared: bool,
+    block_hash: BlockHash,
+}
+
+impl<BlockHash> FileUpload<BlockHash> {
+    fn new<AccountId: Ss58Codec>(block_hash: BlockHash, file: UploadedFile<AccountId>) -> Self {
+        FileUpload {
+            hash: vec_to_hex_string(&file.merkle_root),
+            owner: file.owner.to_ss58check(),
+            size: file.size,
+            pieces: file.pieces,
+            shared: file.shared,
+            block_hash,
+        }
+    }
+}
+
+/// Encoding of the bytes returned by `template_getFileBytes`.
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Encoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    fn encode(&self, bytes: &Vec<u8>) -> String {
+        match self {
+            Encoding::Hex => vec_to_hex_string(bytes),
+            Encoding::Base64 => base64::encode(bytes),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct FileBytes {
+    offset: u32,
+    /// Number of bytes returned, less than requested if the end of the file was reached.
+    length: u32,
+    /// Exact size of the whole file, in bytes.
+    size: u32,
+    encoding: Encoding,
+    data: String,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct MerkleProof {
+    position: u32,
+    pieces: u32,
+    chunk_size: u32,
+    mode: TreeMode,
     content: String,
+    /// Sibling hashes from the leaf up to the root. Single-piece files have none.
+    proof: Vec<String>,
+}
+
+impl From<ChunkProof> for MerkleProof {
+    fn from(proof: ChunkProof) -> Self {
+        MerkleProof {
+            position: proof.leaf_index,
+            pieces: proof.pieces,
+            chunk_size: proof.chunk_size,
+            mode: proof.mode,
+            content: vec_to_hex_string(&proof.content),
+            proof: proof.siblings.iter()
+                .map(|hash| vec_to_hex_string(&hash.to_vec()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct MultiProof {
+    positions: Vec<u32>,
+    pieces: u32,
+    chunk_size: u32,
+    mode: TreeMode,
+    /// Content of every chunk, in the order of `positions`.
+    contents: Vec<String>,
+    /// Hashes needed to rebuild the merkle root, each of them listed once.
     proof: Vec<String>,
 }
 
+impl From<ChunksProof> for MultiProof {
+    fn from(proof: ChunksProof) -> Self {
+        MultiProof {
+            positions: proof.leaf_indices,
+            pieces: proof.pieces,
+            chunk_size: proof.chunk_size,
+            mode: proof.mode,
+            contents: proof.contents.iter()
+                .map(vec_to_hex_string)
+                .collect(),
+            proof: proof.hashes.iter()
+                .map(|hash| vec_to_hex_string(&hash.to_vec()))
+                .collect(),
+        }
+    }
+}
+
 #[rpc(client, server)]
-pub trait TemplateApi<BlockHash> {
+pub trait TemplateApi<BlockHash, BlockNumber> {
     #[method(name = "template_getFiles")]
     fn get_files(&self, at: Option<BlockHash>) -> RpcResult<Vec<HashItem>>;
 
+    #[method(name = "template_getFilesPaged")]
+    fn get_files_paged(&self, at: Option<BlockHash>, start_after: Option<String>, limit: Option<u32>) -> RpcResult<FilesPage>;
+
+    #[method(name = "template_getFilesByOwner")]
+    fn get_files_by_owner(&self, at: Option<BlockHash>, owner: String) -> RpcResult<Vec<HashItem>>;
+
+    #[method(name = "template_getFileInfo")]
+    fn get_file_info(&self, at: Option<BlockHash>, merkle_root: String) -> RpcResult<FileInfo<BlockNumber>>;
+
     #[method(name = "template_getProof")]
     fn get_proof(&self, at: Option<BlockHash>, merkle_root: String, position: u32) -> RpcResult<MerkleProof>;
+
+    #[method(name = "template_getProofs")]
+    fn get_proofs(&self, at: Option<BlockHash>, merkle_root: String, positions: Vec<u32>) -> RpcResult<MultiProof>;
+
+    #[method(name = "template_getFileBytes")]
+    fn get_file_bytes(
+        &self,
+        at: Option<BlockHash>,
+        merkle_root: String,
+        offset: u32,
+        length: u32,
+        encoding: Option<Encoding>,
+    ) -> RpcResult<FileBytes>;
+
+    #[method(name = "template_verifyChunk")]
+    fn verify_chunk(
+        &self,
+        at: Option<BlockHash>,
+        merkle_root: String,
+        position: u32,
+        content: String,
+        proof: Vec<String>,
+        pieces: u32,
+        chunk_size: u32,
+        mode: TreeMode,
+    ) -> RpcResult<bool>;
+
+    #[subscription(
+        name = "template_subscribeFiles" => "template_file",
+        unsubscribe = "template_unsubscribeFiles",
+        item = FileUpload<BlockHash>
+    )]
+    fn subscribe_files(&self, mode: Option<SubscriptionMode>);
 }
 
-/// A struct that implements the `TemplateApi`.
-pub struct TemplatePallet<C, Block> {
+/// A struct that implements the `TemplateApi`, for a chain of `Block`s and `AccountId`s given as
+/// a `(Block, AccountId)` tuple.
+pub struct TemplatePallet<C, M> {
     // If you have more generics, no need to TemplatePallet<C, M, N, P, ...>
     // just use a tuple like TemplatePallet<C, (M, N, P, ...)>
     client: Arc<C>,
-    _marker: std::marker::PhantomData<Block>,
+    /// Runs the tasks feeding the subscriptions.
+    executor: Arc<dyn SpawnNamed>,
+    max_file_bytes: u32,
+    _marker: std::marker::PhantomData<M>,
 }
 
-impl<C, Block> TemplatePallet<C, Block> {
-    /// Create new `TemplatePallet` instance with the given reference to the client.
-    pub fn new(client: Arc<C>) -> Self {
-        Self { client, _marker: Default::default() }
+impl<C, M> TemplatePallet<C, M> {
+    /// Create new `TemplatePallet` instance with the given reference to the client, and the
+    /// executor to run the subscriptions with.
+    pub fn new(client: Arc<C>, executor: Arc<dyn SpawnNamed>) -> Self {
+        Self { client, executor, max_file_bytes: DEFAULT_MAX_FILE_BYTES, _marker: Default::default() }
+    }
+
+    /// Sets the maximum number of bytes `template_getFileBytes` returns at once.
+    pub fn with_max_file_bytes(mut self, max_file_bytes: u32) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self
     }
 }
 
-impl<C, Block> TemplateApiServer<<Block as BlockT>::Hash> for TemplatePallet<C, Block>
+impl<C, Block, AccountId> TemplateApiServer<<Block as BlockT>::Hash, NumberFor<Block>> for TemplatePallet<C, (Block, AccountId)>
     where
         Block: BlockT,
-        C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
-        C::Api: TemplateRuntimeApi<Block>,
+        AccountId: Codec + Ss58Codec + Send + Sync + 'static,
+        C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + BlockBackend<Block> + BlockchainEvents<Block>,
+        C: Send + Sync + 'static,
+        C::Api: TemplateRuntimeApi<Block, AccountId, NumberFor<Block>>,
 {
     fn get_files(&self, at: Option<<Block as BlockT>::Hash>) -> RpcResult<Vec<HashItem>> {
         let api = self.client.runtime_api();
         let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
 
+        if self.is_legacy(&at)? {
+            #[allow(deprecated)]
+            let result = api.get_files_before_version_2(&at).map_err(runtime_error_into_rpc_err)?;
+            return Ok(result.into_iter()
+                .map(|(hash, pieces)| HashItem::from((hash, pieces, LEGACY_CHUNK_SIZE, TreeMode::Standard)))
+                .collect())
+        }
         let result = api.get_files(&at).map_err(runtime_error_into_rpc_err)?;
-        let hashes = result.into_iter().map(|item| HashItem {
-            pieces: item.1,
-            hash: vec_to_hex_string(&item.0),
-        }).collect();
-        Ok(hashes)
+        Ok(result.into_iter().map(HashItem::from).collect())
+    }
+
+    fn get_files_paged(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> RpcResult<FilesPage> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        let start_after = start_after
+            .map(|hash| array_bytes::hex2array(&hash))
+            .transpose()
+            .map_err(invalid_param("start_after"))?;
+
+        // an empty page would have no cursor, as if it were the last one
+        let limit = match limit.unwrap_or(DEFAULT_PAGE_SIZE) {
+            0 => return Err(Error::InvalidParam { param: "limit", reason: "must be at least 1".into() }.into()),
+            limit => limit,
+        };
+        let (files, next_cursor) = api.get_files_paged(&at, start_after, limit)
+            .map_err(runtime_error_into_rpc_err)?;
+        Ok(FilesPage {
+            files: files.into_iter().map(HashItem::from).collect(),
+            next_cursor: next_cursor.map(|hash| vec_to_hex_string(&hash.to_vec())),
+        })
+    }
+
+    fn get_files_by_owner(&self, at: Option<<Block as BlockT>::Hash>, owner: String) -> RpcResult<Vec<HashItem>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        let owner = AccountId::from_ss58check(&owner).map_err(invalid_param("owner"))?;
+
+        let result = api.get_files_by_owner(&at, owner).map_err(runtime_error_into_rpc_err)?;
+        Ok(result.into_iter().map(HashItem::from).collect())
+    }
+
+    fn get_file_info(&self, at: Option<<Block as BlockT>::Hash>, merkle_root: String) -> RpcResult<FileInfo<NumberFor<Block>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        let merkle_root_bytes = array_bytes::hex2bytes(&merkle_root).map_err(invalid_param("merkle_root"))?;
+        let hash = vec_to_hex_string(&merkle_root_bytes);
+        let info = api.get_file_info(&at, merkle_root_bytes).map_err(runtime_error_into_rpc_err)?;
+        Ok(FileInfo::new(hash, info.map_err(Error::File)?))
     }
 
     fn get_proof(&self, at: Option<<Block as BlockT>::Hash>, merkle_root: String, position: u32) -> RpcResult<MerkleProof> {
         let api = self.client.runtime_api();
         let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
-        let merkle_root_bytes = array_bytes::hex2bytes(&merkle_root).map_err(runtime_error_into_rpc_err)?.to_vec();
-        let result = api.get_proof(&at, merkle_root_bytes, position).map_err(runtime_error_into_rpc_err)?;
-        match result {
-            Some((content, proof)) => Ok(MerkleProof {
-                content: vec_to_hex_string(&content),
-                proof: proof.iter()
-                    .map(|hash| vec_to_hex_string(hash))
-                    .collect()
-            }),
-            None => Err(runtime_error_into_rpc_err("Failure getting the merkle proof"))
+        let merkle_root_bytes = array_bytes::hex2bytes(&merkle_root).map_err(invalid_param("merkle_root"))?;
+        if self.is_legacy(&at)? {
+            return self.legacy_proof(&at, merkle_root_bytes, position)
+        }
+        let result = match api.get_proof(&at, merkle_root_bytes.clone(), position).map_err(runtime_error_into_rpc_err)? {
+            // files stored with `store_indexed` only keep their bytes in the indexed block bodies
+            Err(FileError::Indexed) => {
+                let file_bytes = self.indexed_file(&merkle_root_bytes)?;
+                api.get_indexed_proof(&at, merkle_root_bytes, file_bytes, position)
+                    .map_err(runtime_error_into_rpc_err)?
+            },
+            result => result,
+        };
+        Ok(result.map_err(Error::File)?.into())
+    }
+
+    fn get_proofs(&self, at: Option<<Block as BlockT>::Hash>, merkle_root: String, positions: Vec<u32>) -> RpcResult<MultiProof> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        let merkle_root_bytes = array_bytes::hex2bytes(&merkle_root).map_err(invalid_param("merkle_root"))?;
+        let result = match api.get_proofs(&at, merkle_root_bytes.clone(), positions.clone())
+            .map_err(runtime_error_into_rpc_err)?
+        {
+            // files stored with `store_indexed` only keep their bytes in the indexed block bodies
+            Err(FileError::Indexed) => {
+                let file_bytes = self.indexed_file(&merkle_root_bytes)?;
+                api.get_indexed_proofs(&at, merkle_root_bytes, file_bytes, positions)
+                    .map_err(runtime_error_into_rpc_err)?
+            },
+            result => result,
+        };
+        Ok(result.map_err(Error::File)?.into())
+    }
+
+    fn get_file_bytes(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+        merkle_root: String,
+        offset: u32,
+        length: u32,
+        encoding: Option<Encoding>,
+    ) -> RpcResult<FileBytes> {
+        if length > self.max_file_bytes {
+            return Err(Error::TooManyBytes { requested: length, max: self.max_file_bytes }.into())
+        }
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        let merkle_root_bytes = array_bytes::hex2bytes(&merkle_root).map_err(invalid_param("merkle_root"))?;
+        let size = api.get_file_info(&at, merkle_root_bytes.clone())
+            .map_err(runtime_error_into_rpc_err)?
+            .map_err(Error::File)?
+            .size;
+        let bytes: Vec<u8> = match api.get_file_bytes(&at, merkle_root_bytes.clone(), offset, length)
+            .map_err(runtime_error_into_rpc_err)?
+        {
+            // files stored with `store_indexed` only keep their bytes in the indexed block bodies,
+            // which can only be read whole. They are checked against the merkle root like for
+            // their proofs, and only the requested range is returned.
+            Err(FileError::Indexed) => {
+                let file_bytes = self.indexed_file(&merkle_root_bytes)?;
+                api.get_indexed_file_bytes(&at, merkle_root_bytes, file_bytes, offset, length)
+                    .map_err(runtime_error_into_rpc_err)?
+                    .map_err(Error::File)?
+            },
+            result => result.map_err(Error::File)?,
+        };
+        let encoding = encoding.unwrap_or_default();
+        Ok(FileBytes {
+            offset,
+            length: bytes.len() as u32,
+            size,
+            encoding,
+            data: encoding.encode(&bytes),
+        })
+    }
+
+    fn verify_chunk(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+        merkle_root: String,
+        position: u32,
+        content: String,
+        proof: Vec<String>,
+        pieces: u32,
+        chunk_size: u32,
+        mode: TreeMode,
+    ) -> RpcResult<bool> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        let merkle_root_bytes = array_bytes::hex2bytes(&merkle_root).map_err(invalid_param("merkle_root"))?;
+        let content_bytes = array_bytes::hex2bytes(&content).map_err(invalid_param("content"))?;
+        let siblings = proof.iter()
+            .map(|hash| array_bytes::hex2array(hash))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid_param("proof"))?;
+        api.verify_chunk(&at, merkle_root_bytes, position, content_bytes, siblings, pieces, chunk_size, mode)
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn subscribe_files(&self, mut sink: SubscriptionSink, mode: Option<SubscriptionMode>) -> SubscriptionResult {
+        let blocks = match mode.unwrap_or_default() {
+            SubscriptionMode::Imported => self.client.import_notification_stream()
+                .map(|notification| vec![notification.hash])
+                .boxed(),
+            SubscriptionMode::Finalized => self.client.finality_notification_stream()
+                .map(|notification| {
+                    // the blocks finalized along with the notified one come first
+                    let mut hashes = notification.tree_route.to_vec();
+                    hashes.push(notification.hash);
+                    hashes
+                })
+                .boxed(),
+        };
+        let client = self.client.clone();
+        let uploads = blocks
+            .then(move |hashes| {
+                let client = client.clone();
+                // runtime calls block, so they are kept off the threads running the async tasks
+                tokio::task::spawn_blocking(move || uploaded_files::<_, Block, AccountId>(&*client, hashes))
+                    .map(|uploads| uploads.unwrap_or_else(|err| {
+                        log::warn!(target: LOG_TARGET, "Failed to read the uploaded files: {}", err);
+                        Vec::new()
+                    }))
+            })
+            .flat_map(futures::stream::iter);
+
+        let fut = async move {
+            sink.pipe_from_stream(uploads).await;
+        };
+        self.executor.spawn("template-rpc-subscription", Some("rpc"), fut.boxed());
+        Ok(())
+    }
+}
+
+impl<C, Block, AccountId> TemplatePallet<C, (Block, AccountId)>
+    where
+        Block: BlockT,
+        AccountId: Codec,
+        C: ProvideRuntimeApi<Block>,
+        C::Api: TemplateRuntimeApi<Block, AccountId, NumberFor<Block>>,
+{
+    /// Whether the runtime at the given block has the version 1 of the runtime API, which only
+    /// has `get_files` and `get_proof`, with their former signatures.
+    fn is_legacy(&self, at: &BlockId<Block>) -> RpcResult<bool> {
+        let version = self.client.runtime_api()
+            .api_version::<dyn TemplateRuntimeApi<Block, AccountId, NumberFor<Block>>>(at)
+            .map_err(runtime_error_into_rpc_err)?;
+        Ok(version == Some(1))
+    }
+
+    /// Proof of a chunk out of a runtime with the version 1 of the runtime API, whose proofs have
+    /// neither the number of pieces of the file nor typed errors.
+    #[allow(deprecated)]
+    fn legacy_proof(&self, at: &BlockId<Block>, merkle_root: Vec<u8>, position: u32) -> RpcResult<MerkleProof> {
+        let api = self.client.runtime_api();
+        let pieces = api.get_files_before_version_2(at)
+            .map_err(runtime_error_into_rpc_err)?
+            .into_iter()
+            .find_map(|(hash, pieces)| (hash == merkle_root).then_some(pieces))
+            .ok_or(Error::File(FileError::FileNotFound))?;
+        let (content, siblings) = api.get_proof_before_version_2(at, merkle_root, position)
+            .map_err(runtime_error_into_rpc_err)?
+            .ok_or(Error::File(FileError::PositionOutOfRange { position, pieces }))?;
+        let siblings = siblings.into_iter()
+            .map(|hash| hash.try_into())
+            .collect::<Result<Vec<Hash>, _>>()
+            .map_err(runtime_error_into_rpc_err)?;
+        Ok(ChunkProof {
+            leaf_index: position,
+            pieces,
+            chunk_size: LEGACY_CHUNK_SIZE,
+            mode: TreeMode::Standard,
+            siblings,
+            content,
+        }.into())
+    }
+}
+
+/// Files uploaded in the given blocks, in order. Every block gets its own runtime API instance,
+/// so that the changes of one block never show in the next. Blocks whose state is already pruned
+/// are skipped.
+fn uploaded_files<C, Block, AccountId>(client: &C, hashes: Vec<<Block as BlockT>::Hash>) -> Vec<FileUpload<<Block as BlockT>::Hash>>
+    where
+        Block: BlockT,
+        AccountId: Codec + Ss58Codec,
+        C: ProvideRuntimeApi<Block>,
+        C::Api: TemplateRuntimeApi<Block, AccountId, NumberFor<Block>>,
+{
+    hashes.into_iter()
+        .flat_map(|hash| {
+            let files = client.runtime_api().get_uploaded_files(&BlockId::hash(hash)).unwrap_or_else(|err| {
+                log::warn!(target: LOG_TARGET, "Failed to read the files uploaded in block {:?}: {}", hash, err);
+                Vec::new()
+            });
+            files.into_iter().map(move |file| FileUpload::new(hash, file))
+        })
+        .collect()
+}
+
+impl<C, Block, AccountId> TemplatePallet<C, (Block, AccountId)>
+    where
+        Block: BlockT,
+        C: BlockBackend<Block>,
+{
+    /// Bytes of a file stored with `store_indexed`, which the node keeps in the indexed body of
+    /// its block under the merkle root of the file. Fails with `BYTES_UNAVAILABLE` if the node
+    /// does not have them, because the block body was pruned.
+    fn indexed_file(&self, merkle_root: &[u8]) -> RpcResult<Vec<u8>> {
+        let hash = <Block as BlockT>::Hash::decode(&mut &merkle_root[..])
+            .map_err(|_| Error::File(FileError::InvalidMerkleRoot))?;
+        match self.client.indexed_transaction(hash).map_err(runtime_error_into_rpc_err)? {
+            Some(file_bytes) => Ok(file_bytes),
+            None => Err(Error::BytesUnavailable { merkle_root: vec_to_hex_string(&merkle_root.to_vec()) }.into()),
         }
     }
 }
 
-const RUNTIME_ERROR: i32 = 1;
+/// Calling the runtime API failed.
+pub const RUNTIME_ERROR: i32 = 1;
+/// A parameter is malformed: not hexadecimal, not an SS58 address, not a valid proof.
+pub const INVALID_PARAM: i32 = 2;
+/// The merkle root does not have the length of a hash.
+pub const INVALID_MERKLE_ROOT: i32 = 3;
+/// No file is stored under the merkle root.
+pub const FILE_NOT_FOUND: i32 = 4;
+/// The file has no chunk at the requested position.
+pub const POSITION_OUT_OF_RANGE: i32 = 5;
+/// The requested offset is past the end of the file.
+pub const OFFSET_OUT_OF_RANGE: i32 = 6;
+/// No chunk position was requested.
+pub const NO_POSITIONS: i32 = 7;
+/// More bytes were requested than the node returns at once.
+pub const TOO_MANY_BYTES: i32 = 8;
+/// The node does not have the bytes of a file stored with `store_indexed`, because their block
+/// body was pruned.
+pub const BYTES_UNAVAILABLE: i32 = 9;
+/// The file was stored with `store_indexed`, so its bytes are not in the state.
+pub const FILE_INDEXED: i32 = 10;
+/// The file was not stored with `store_indexed`.
+pub const FILE_NOT_INDEXED: i32 = 11;
+/// The indexed bytes of the file do not match its merkle root.
+pub const BYTES_MISMATCH: i32 = 12;
+/// Some chunk or merkle tree node of the file is missing from the state.
+pub const INCOMPLETE_FILE: i32 = 13;
+
+/// Errors of the template RPC methods. Each of them has its own code, and a structured `data`
+/// payload, so that clients can tell a missing file from a bad request.
+#[derive(Debug)]
+pub enum Error {
+    /// Calling the runtime API failed.
+    Runtime(String),
+    /// A parameter could not be parsed.
+    InvalidParam { param: &'static str, reason: String },
+    /// The runtime could not serve the requested file.
+    File(FileError),
+    /// More bytes than allowed were requested at once.
+    TooManyBytes { requested: u32, max: u32 },
+    /// The node does not have the bytes of a file stored with `store_indexed`.
+    BytesUnavailable { merkle_root: String },
+}
+
+impl From<Error> for JsonRpseeError {
+    fn from(error: Error) -> Self {
+        let (code, message, data) = match error {
+            Error::Runtime(reason) => (RUNTIME_ERROR, "Runtime error", json!({ "reason": reason })),
+            Error::InvalidParam { param, reason } =>
+                (INVALID_PARAM, "Invalid parameter", json!({ "param": param, "reason": reason })),
+            Error::File(error) => {
+                let (code, message) = match error {
+                    FileError::InvalidMerkleRoot => (INVALID_MERKLE_ROOT, "Invalid merkle root"),
+                    FileError::FileNotFound => (FILE_NOT_FOUND, "File not found"),
+                    FileError::PositionOutOfRange { .. } => (POSITION_OUT_OF_RANGE, "Position out of the file"),
+                    FileError::OffsetOutOfRange { .. } => (OFFSET_OUT_OF_RANGE, "Offset out of the file"),
+                    FileError::NoPositions => (NO_POSITIONS, "No position requested"),
+                    FileError::Indexed => (FILE_INDEXED, "File stored with store_indexed"),
+                    FileError::NotIndexed => (FILE_NOT_INDEXED, "File not stored with store_indexed"),
+                    FileError::BytesMismatch => (BYTES_MISMATCH, "File bytes do not match the merkle root"),
+                    FileError::IncompleteFile => (INCOMPLETE_FILE, "Incomplete file"),
+                };
+                (code, message, json!(error))
+            },
+            Error::TooManyBytes { requested, max } =>
+                (TOO_MANY_BYTES, "Too many bytes requested", json!({ "requested": requested, "max": max })),
+            Error::BytesUnavailable { merkle_root } =>
+                (BYTES_UNAVAILABLE, "File bytes unavailable", json!({ "merkle_root": merkle_root })),
+        };
+        CallError::Custom(ErrorObject::owned(code, message, Some(data))).into()
+    }
+}
 
 fn vec_to_hex_string(data: &Vec<u8>) -> String {
     data.iter()
@@ -95,10 +659,10 @@ fn vec_to_hex_string(data: &Vec<u8>) -> String {
 
 /// Converts a runtime trap into an RPC error.
 fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> JsonRpseeError {
-    CallError::Custom(ErrorObject::owned(
-        RUNTIME_ERROR,
-        "Runtime error",
-        Some(format!("{:?}", err)),
-    ))
-        .into()
-}
\ No newline at end of file
+    Error::Runtime(format!("{:?}", err)).into()
+}
+
+/// Converts a parsing failure of the given parameter into an RPC error.
+fn invalid_param<E: std::fmt::Debug>(param: &'static str) -> impl FnOnce(E) -> Error {
+    move |err| Error::InvalidParam { param, reason: format!("{:?}", err) }
+}
@@ -0,0 +1,505 @@
+//! Benchmarking setup for pallet-template
+
+use super::*;
+
+use codec::{Decode, Encode};
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::traits::{Currency, Get};
+use frame_system::RawOrigin;
+use sp_runtime::traits::{Bounded, Hash as _, StaticLookup, Zero};
+use sp_std::{vec, vec::Vec};
+
+/// Smallest chunk size exponent that divides a file of `size` bytes in at most `pieces` chunks.
+fn chunk_size_exponent<T: Config>(size: u32, pieces: u32) -> u8 {
+    let mut exponent = T::MinChunkSizeExponent::get();
+    while exponent < T::MaxChunkSizeExponent::get() && (size as u64) > (pieces as u64) << exponent {
+        exponent += 1;
+    }
+    exponent
+}
+
+/// Size and chunk size exponent of a file of exactly `pieces` pieces, as close to `size` bytes as
+/// the chunk sizes allow. The size only differs from `size` when no chunk size divides a file of
+/// `size` bytes in `pieces` pieces.
+fn file_layout<T: Config>(size: u32, pieces: u32) -> (u32, u8) {
+    let mut exponent = chunk_size_exponent::<T>(size, pieces);
+    if ((pieces as u64 - 1) << exponent) >= T::MaxFileSize::get() as u64 {
+        // even the smallest file of `pieces` chunks of that size would be too big
+        exponent -= 1;
+    }
+    let min_size = ((pieces as u64 - 1) << exponent) + 1;
+    let max_size = (pieces as u64) << exponent;
+    ((size as u64).clamp(min_size, max_size) as u32, exponent)
+}
+
+fn file_root<T: Config>(file_bytes: Vec<u8>, chunk_size_exponent: u8) -> T::Hash {
+    let tree = FileMerkleTreeOf::<T>::new(file_bytes, 1 << chunk_size_exponent)
+        .expect("benchmark files are within bounds; qed");
+    T::Hash::decode(&mut tree.merkle_root()).expect("merkle roots are 32 bytes long; qed")
+}
+
+/// Opens an upload session for `file_bytes` and uploads all of its parts.
+fn upload_session<T: Config>(caller: &T::AccountId, file_bytes: &[u8], chunk_size_exponent: u8) -> SessionId {
+    let session_id = NextSessionId::<T>::get();
+    Pallet::<T>::begin_upload(
+        RawOrigin::Signed(caller.clone()).into(),
+        file_bytes.len() as u32,
+        file_root::<T>(file_bytes.to_vec(), chunk_size_exponent),
+        chunk_size_exponent,
+        TreeMode::Standard,
+        None,
+    ).expect("benchmark sessions are valid; qed");
+    for (index, part) in file_bytes.chunks(T::MaxPartSize::get() as usize).enumerate() {
+        Pallet::<T>::upload_part(
+            RawOrigin::Signed(caller.clone()).into(),
+            session_id,
+            index as u32,
+            part.to_vec(),
+        ).expect("benchmark parts are valid; qed");
+    }
+    session_id
+}
+
+/// Stores a file of `pieces` chunks of the minimum size, keeping the given merkle tree nodes.
+fn stored_file<T: Config>(pieces: u32, tree_storage: TreeStorage) -> T::Hash {
+    let caller: T::AccountId = whitelisted_caller();
+    T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+    let exponent = T::MinChunkSizeExponent::get();
+    let file_bytes = vec![1u8; (pieces as usize) << exponent];
+    let merkle_root = file_root::<T>(file_bytes.clone(), exponent);
+    let tree = FileMerkleTreeOf::<T>::new(file_bytes, 1 << exponent)
+        .expect("benchmark files are within bounds; qed");
+    Pallet::<T>::store_file(caller, merkle_root, tree, tree_storage, None)
+        .expect("benchmark files are valid; qed");
+    merkle_root
+}
+
+/// Stores a file with all of its merkle tree nodes and removes it, so that its chunks and nodes
+/// are pending deletion. Uploading the file again clears them first, which is the worst case of
+/// every upload.
+fn removed_file<T: Config>(file_bytes: Vec<u8>, chunk_size_exponent: u8) {
+    let caller: T::AccountId = whitelisted_caller();
+    let merkle_root = file_root::<T>(file_bytes.clone(), chunk_size_exponent);
+    let tree = FileMerkleTreeOf::<T>::new(file_bytes, 1 << chunk_size_exponent)
+        .expect("benchmark files are within bounds; qed");
+    Pallet::<T>::store_file(caller.clone(), merkle_root, tree, TreeStorage::Full, None)
+        .expect("benchmark files are valid; qed");
+    Pallet::<T>::remove_file(RawOrigin::Signed(caller).into(), merkle_root)
+        .expect("the caller owns the file; qed");
+}
+
+/// Grants manager rights over a stored file to `count` accounts, on behalf of the caller.
+fn add_managers<T: Config>(merkle_root: T::Hash, count: u32) {
+    let caller: T::AccountId = whitelisted_caller();
+    for index in 0 .. count {
+        let manager: T::AccountId = account("manager", index, 0);
+        Pallet::<T>::add_manager(
+            RawOrigin::Signed(caller.clone()).into(),
+            merkle_root,
+            T::Lookup::unlookup(manager),
+        ).expect("benchmark managers are within bounds; qed");
+    }
+}
+
+/// Adds `count` accounts, which are not registered, to the list of storage providers.
+fn other_providers<T: Config>(count: u32) {
+    for index in 0 .. count {
+        let provider: T::AccountId = account("provider", index, 0);
+        ProviderAccounts::<T>::try_append(provider).expect("at most `MaxProviders` accounts; qed");
+    }
+}
+
+/// Registers the caller as a storage provider of the given file, and challenges it.
+fn challenged_provider<T: Config>(merkle_root: T::Hash) -> ChallengeId {
+    let caller: T::AccountId = whitelisted_caller();
+    T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+    Pallet::<T>::register_provider(RawOrigin::Signed(caller.clone()).into())
+        .expect("the caller is not a provider yet; qed");
+    Pallet::<T>::declare_file(RawOrigin::Signed(caller).into(), merkle_root)
+        .expect("the file is stored; qed");
+    let challenge_id = NextChallengeId::<T>::get();
+    // the caller is the only provider holding files, so it is always picked
+    Pallet::<T>::issue_challenge(frame_system::Pallet::<T>::block_number(), 0);
+    challenge_id
+}
+
+benchmarks! {
+    upload_file {
+        // file size, in bytes
+        let s in 1 .. T::MaxFileSize::get();
+        // number of pieces, which the chunk size is picked for
+        let p in 1 .. T::MaxPieces::get();
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        let (size, exponent) = file_layout::<T>(s, p);
+        let file_bytes = vec![1u8; size as usize];
+        let merkle_root = file_root::<T>(file_bytes.clone(), exponent);
+        removed_file::<T>(file_bytes.clone(), exponent);
+    }: _(RawOrigin::Signed(caller), file_bytes, exponent, TreeMode::Standard, Some(1u32.into()))
+    verify {
+        assert_eq!(Files::<T>::get(merkle_root).map(|file| file.pieces), Some(p));
+    }
+
+    store_indexed {
+        // file size, in bytes
+        let s in 1 .. T::MaxFileSize::get();
+        // number of pieces, which the chunk size is picked for
+        let p in 1 .. T::MaxPieces::get();
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        let (size, exponent) = file_layout::<T>(s, p);
+        let file_bytes = vec![1u8; size as usize];
+        let merkle_root = file_root::<T>(file_bytes.clone(), exponent);
+        removed_file::<T>(file_bytes.clone(), exponent);
+        frame_system::Pallet::<T>::set_extrinsic_index(0);
+    }: _(RawOrigin::Signed(caller), exponent, TreeMode::Standard, Some(1u32.into()), file_bytes)
+    verify {
+        assert_eq!(Files::<T>::get(merkle_root).map(|file| file.pieces), Some(p));
+    }
+
+    remove_file {
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        // the chunks and merkle tree nodes are deleted afterwards, so the file size does not matter
+        let exponent = T::MinChunkSizeExponent::get();
+        let file_bytes = vec![1u8; 1 << exponent];
+        let merkle_root = file_root::<T>(file_bytes.clone(), exponent);
+        Pallet::<T>::upload_file(
+            RawOrigin::Signed(caller.clone()).into(),
+            file_bytes,
+            exponent,
+            TreeMode::Standard,
+            Some(1u32.into()),
+        )?;
+    }: _(RawOrigin::Signed(caller), merkle_root)
+    verify {
+        assert!(!Files::<T>::contains_key(merkle_root));
+        assert!(PendingDeletions::<T>::contains_key(merkle_root));
+    }
+
+    begin_upload {
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        let session_id = NextSessionId::<T>::get();
+        let exponent = T::MinChunkSizeExponent::get();
+    }: _(RawOrigin::Signed(caller), T::MaxFileSize::get(), T::Hash::default(), exponent, TreeMode::Standard, Some(1u32.into()))
+    verify {
+        assert!(UploadSessions::<T>::contains_key(session_id));
+    }
+
+    upload_part {
+        // part size, in bytes
+        let b in 1 .. T::MaxPartSize::get();
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        let session_id = NextSessionId::<T>::get();
+        Pallet::<T>::begin_upload(
+            RawOrigin::Signed(caller.clone()).into(),
+            b,
+            T::Hash::default(),
+            T::MinChunkSizeExponent::get(),
+            TreeMode::Standard,
+            None,
+        )?;
+    }: _(RawOrigin::Signed(caller), session_id, 0, vec![1u8; b as usize])
+    verify {
+        assert!(UploadParts::<T>::contains_key(session_id, 0));
+    }
+
+    finalize_upload {
+        // file size, in bytes
+        let s in 1 .. T::MaxFileSize::get();
+        // number of pieces, which the chunk size is picked for
+        let p in 1 .. T::MaxPieces::get();
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        let (size, exponent) = file_layout::<T>(s, p);
+        let file_bytes = vec![1u8; size as usize];
+        let merkle_root = file_root::<T>(file_bytes.clone(), exponent);
+        removed_file::<T>(file_bytes.clone(), exponent);
+        let session_id = upload_session::<T>(&caller, &file_bytes, exponent);
+    }: _(RawOrigin::Signed(caller), session_id, size, p)
+    verify {
+        assert_eq!(Files::<T>::get(merkle_root).map(|file| file.pieces), Some(p));
+        assert!(!UploadSessions::<T>::contains_key(session_id));
+    }
+
+    abort_upload {
+        // number of parts uploaded
+        let p in 0 .. Pallet::<T>::parts_for(T::MaxFileSize::get());
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        let session_id = NextSessionId::<T>::get();
+        Pallet::<T>::begin_upload(
+            RawOrigin::Signed(caller.clone()).into(),
+            T::MaxFileSize::get(),
+            T::Hash::default(),
+            T::MinChunkSizeExponent::get(),
+            TreeMode::Standard,
+            None,
+        )?;
+        let file_bytes = vec![1u8; T::MaxFileSize::get() as usize];
+        for (index, part) in file_bytes.chunks(T::MaxPartSize::get() as usize).take(p as usize).enumerate() {
+            Pallet::<T>::upload_part(
+                RawOrigin::Signed(caller.clone()).into(),
+                session_id,
+                index as u32,
+                part.to_vec(),
+            )?;
+        }
+    }: {
+        Pallet::<T>::abort_session(session_id);
+    }
+    verify {
+        assert!(!UploadSessions::<T>::contains_key(session_id));
+    }
+
+    delete_file_items {
+        // number of chunks and merkle tree nodes deleted
+        let n in 0 .. 1000;
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        // a file with more chunks and nodes than deleted at once
+        let exponent = T::MinChunkSizeExponent::get();
+        let file_bytes = vec![1u8; (T::MaxPieces::get() as usize) << exponent];
+        let merkle_root = file_root::<T>(file_bytes.clone(), exponent);
+        Pallet::<T>::upload_file(
+            RawOrigin::Signed(caller.clone()).into(),
+            file_bytes,
+            exponent,
+            TreeMode::Standard,
+            None,
+        )?;
+        Pallet::<T>::remove_file(RawOrigin::Signed(caller).into(), merkle_root)?;
+    }: {
+        Pallet::<T>::delete_file_items(merkle_root, n);
+    }
+    verify {
+        assert!(PendingDeletions::<T>::contains_key(merkle_root));
+    }
+
+    set_file_metadata {
+        let caller: T::AccountId = whitelisted_caller();
+        let merkle_root = stored_file::<T>(1, TreeStorage::Full);
+        let name = vec![b'a'; T::MaxNameLength::get() as usize];
+        let content_type = vec![b'a'; T::MaxContentTypeLength::get() as usize];
+        let description = vec![b'a'; T::MaxDescriptionLength::get() as usize];
+    }: _(RawOrigin::Signed(caller), merkle_root, name.clone(), content_type, Some(description))
+    verify {
+        assert_eq!(Files::<T>::get(merkle_root).map(|file| file.name.into_inner()), Some(name));
+    }
+
+    transfer_file {
+        let caller: T::AccountId = whitelisted_caller();
+        let new_owner: T::AccountId = account("new_owner", 0, 0);
+        let merkle_root = stored_file::<T>(1, TreeStorage::Full);
+        add_managers::<T>(merkle_root, T::MaxManagers::get());
+    }: _(RawOrigin::Signed(caller), merkle_root, T::Lookup::unlookup(new_owner.clone()))
+    verify {
+        assert!(FilesByOwner::<T>::contains_key(new_owner, merkle_root));
+    }
+
+    add_manager {
+        let caller: T::AccountId = whitelisted_caller();
+        let manager: T::AccountId = account("manager", T::MaxManagers::get(), 0);
+        let merkle_root = stored_file::<T>(1, TreeStorage::Full);
+        add_managers::<T>(merkle_root, T::MaxManagers::get() - 1);
+    }: _(RawOrigin::Signed(caller), merkle_root, T::Lookup::unlookup(manager.clone()))
+    verify {
+        let managers = Files::<T>::get(merkle_root).map(|file| file.managers).unwrap_or_default();
+        assert!(managers.iter().any(|(account, _)| account == &manager));
+    }
+
+    remove_manager {
+        let caller: T::AccountId = whitelisted_caller();
+        let manager: T::AccountId = account("manager", T::MaxManagers::get() - 1, 0);
+        let merkle_root = stored_file::<T>(1, TreeStorage::Full);
+        add_managers::<T>(merkle_root, T::MaxManagers::get());
+    }: _(RawOrigin::Signed(caller), merkle_root, T::Lookup::unlookup(manager.clone()))
+    verify {
+        let managers = Files::<T>::get(merkle_root).map(|file| file.managers).unwrap_or_default();
+        assert!(!managers.iter().any(|(account, _)| account == &manager));
+    }
+
+    renew_file {
+        let caller: T::AccountId = whitelisted_caller();
+        let merkle_root = stored_file::<T>(1, TreeStorage::Full);
+        let expires_at: T::BlockNumber = 1u32.into();
+        // the blocks the file could expire at after the renewal are full, but for the last one
+        let delay = T::MaxExpiryDelay::get();
+        let renewed_at = expires_at + 1u32.into() + delay.into();
+        for block in 0 ..= delay {
+            let other_roots = (block * T::MaxExpiriesPerBlock::get() .. (block + 1) * T::MaxExpiriesPerBlock::get())
+                .skip((block == delay) as usize)
+                .map(|index| T::Hashing::hash_of(&index))
+                .collect::<Vec<T::Hash>>();
+            FilesExpiring::<T>::insert(
+                expires_at + 1u32.into() + block.into(),
+                BoundedVec::try_from(other_roots).expect("at most `MaxExpiriesPerBlock` files; qed"),
+            );
+        }
+        FilesExpiring::<T>::insert(expires_at, BoundedVec::try_from(vec![merkle_root]).expect("a single file; qed"));
+        Files::<T>::mutate(merkle_root, |file| if let Some(file) = file {
+            file.expires_at = Some(expires_at);
+        });
+    }: _(RawOrigin::Signed(caller), merkle_root, 1u32.into())
+    verify {
+        assert_eq!(Files::<T>::get(merkle_root).and_then(|file| file.expires_at), Some(renewed_at));
+    }
+
+    expire_file {
+        let merkle_root = stored_file::<T>(1, TreeStorage::Full);
+        // the owners do not reserve any deposit, as only their number matters
+        Files::<T>::mutate(merkle_root, |file| if let Some(file) = file {
+            for index in 1 .. T::MaxOwners::get() {
+                let owner: T::AccountId = account("owner", index, 0);
+                FilesByOwner::<T>::insert(&owner, merkle_root, ());
+                file.owners.try_push((owner, Zero::zero())).expect("less than `MaxOwners` owners; qed");
+            }
+        });
+    }: {
+        Pallet::<T>::expire_file(merkle_root);
+    }
+    verify {
+        assert!(!Files::<T>::contains_key(merkle_root));
+        assert!(PendingDeletions::<T>::contains_key(merkle_root));
+    }
+
+    register_provider {
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        other_providers::<T>(T::MaxProviders::get() - 1);
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(Providers::<T>::contains_key(caller));
+    }
+
+    unregister_provider {
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        other_providers::<T>(T::MaxProviders::get() - 1);
+        Pallet::<T>::register_provider(RawOrigin::Signed(caller.clone()).into())
+            .expect("the caller is not a provider yet; qed");
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(!Providers::<T>::contains_key(caller));
+    }
+
+    declare_file {
+        let caller: T::AccountId = whitelisted_caller();
+        let merkle_root = stored_file::<T>(1, TreeStorage::Indexed);
+        Pallet::<T>::register_provider(RawOrigin::Signed(caller.clone()).into())
+            .expect("the caller is not a provider yet; qed");
+        // the declared files do not need to be stored, only the new one is checked
+        Providers::<T>::mutate(&caller, |provider| if let Some(provider) = provider {
+            provider.files = (1 .. T::MaxProviderFiles::get())
+                .map(|index| T::Hashing::hash_of(&index))
+                .collect::<Vec<T::Hash>>()
+                .try_into()
+                .expect("less than `MaxProviderFiles` files; qed");
+        });
+    }: _(RawOrigin::Signed(caller.clone()), merkle_root)
+    verify {
+        let files = Providers::<T>::get(caller).map(|provider| provider.files).unwrap_or_default();
+        assert!(files.contains(&merkle_root));
+    }
+
+    undeclare_file {
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+        Pallet::<T>::register_provider(RawOrigin::Signed(caller.clone()).into())
+            .expect("the caller is not a provider yet; qed");
+        let files = (0 .. T::MaxProviderFiles::get())
+            .map(|index| T::Hashing::hash_of(&index))
+            .collect::<Vec<T::Hash>>();
+        let merkle_root = files[files.len() - 1];
+        Providers::<T>::mutate(&caller, |provider| if let Some(provider) = provider {
+            provider.files = files.try_into().expect("`MaxProviderFiles` files; qed");
+        });
+    }: _(RawOrigin::Signed(caller.clone()), merkle_root)
+    verify {
+        let files = Providers::<T>::get(caller).map(|provider| provider.files).unwrap_or_default();
+        assert!(!files.contains(&merkle_root));
+    }
+
+    respond_challenge {
+        // size of the challenged chunk, in bytes
+        let s in 1 .. MaxChunkSize::<T>::get();
+        // number of sibling hashes of the proof
+        let h in 0 .. MaxProofDepth::<T>::get();
+        let caller: T::AccountId = whitelisted_caller();
+        let challenge_id = challenged_provider::<T>(stored_file::<T>(1, TreeStorage::Indexed));
+        // the challenge is turned into one about the first chunk of a file of `2^h` pieces, whose
+        // merkle root is built from the proof itself, so that the file does not need to be stored
+        let content = vec![1u8; s as usize];
+        let siblings = vec![[1u8; 32]; h as usize];
+        let chunk_size = 1 << chunk_size_exponent::<T>(s, 1);
+        let mut merkle_root = TreeMode::Standard.hash_leaf::<T::FileHasher>(&content, chunk_size);
+        for sibling in siblings.iter() {
+            merkle_root = TreeMode::Standard.hash_node::<T::FileHasher>(&merkle_root, sibling);
+        }
+        Challenges::<T>::mutate(&caller, challenge_id, |challenge| if let Some(challenge) = challenge {
+            challenge.merkle_root = T::Hash::decode(&mut &merkle_root[..])
+                .expect("merkle roots are 32 bytes long; qed");
+            challenge.chunk_index = 0;
+            challenge.pieces = 1 << h;
+            challenge.chunk_size = chunk_size;
+            challenge.mode = TreeMode::Standard;
+        });
+        let content = content.try_into().expect("`s` is at most `MaxChunkSize`; qed");
+        let siblings = siblings.try_into().expect("`h` is at most `MaxProofDepth`; qed");
+    }: _(RawOrigin::Signed(caller.clone()), challenge_id, content, siblings)
+    verify {
+        assert!(!Challenges::<T>::contains_key(caller, challenge_id));
+    }
+
+    issue_challenge {
+        let merkle_root = stored_file::<T>(1, TreeStorage::Indexed);
+        let challenge_id = challenged_provider::<T>(merkle_root);
+    }: {
+        Pallet::<T>::issue_challenge(frame_system::Pallet::<T>::block_number(), 1);
+    }
+    verify {
+        assert_eq!(NextChallengeId::<T>::get(), challenge_id + 2);
+    }
+
+    expire_challenge {
+        let caller: T::AccountId = whitelisted_caller();
+        let merkle_root = stored_file::<T>(1, TreeStorage::Indexed);
+        let challenge_id = challenged_provider::<T>(merkle_root);
+        other_providers::<T>(T::MaxProviders::get() - 1);
+    }: {
+        Pallet::<T>::expire_challenge(caller.clone(), challenge_id);
+    }
+    verify {
+        assert!(!Providers::<T>::contains_key(caller));
+    }
+
+    // The following benchmarks measure the cost of serving a proof for every `TreeStorage`.
+    // They are not used for extrinsics, but show how much is traded for the state saved.
+    get_proof_full {
+        // number of pieces of the file
+        let p in 1 .. T::MaxPieces::get();
+        let merkle_root = stored_file::<T>(p, TreeStorage::Full);
+    }: {
+        Pallet::<T>::get_proof(merkle_root.encode(), p - 1).expect("the piece is stored; qed");
+    }
+
+    get_proof_leaves {
+        // number of pieces of the file
+        let p in 1 .. T::MaxPieces::get();
+        let merkle_root = stored_file::<T>(p, TreeStorage::Leaves);
+    }: {
+        Pallet::<T>::get_proof(merkle_root.encode(), p - 1).expect("the piece is stored; qed");
+    }
+
+    get_proof_root_only {
+        // number of pieces of the file
+        let p in 1 .. T::MaxPieces::get();
+        let merkle_root = stored_file::<T>(p, TreeStorage::RootOnly);
+    }: {
+        Pallet::<T>::get_proof(merkle_root.encode(), p - 1).expect("the piece is stored; qed");
+    }
+
+    impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
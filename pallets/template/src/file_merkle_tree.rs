@@ -1,173 +1,203 @@
-use codec::{Decode, Encode, EncodeLike};
-use scale_info::{Path, Type, TypeInfo};
-use scale_info::build::Fields;
-use sp_io::hashing::sha2_256;
-use sp_std::vec;
+use frame_support::{CloneNoBound, DefaultNoBound, PartialEqNoBound};
+use pallet_template_merkle_proof::{FileHasher, Hash, MultiProof, TreeMode, BEP52_BLOCK_SIZE, HASH_SIZE};
+use sp_std::marker::PhantomData;
 use sp_std::vec::Vec;
 
-/// File chunks to build the merkle tree are hardcoded to 1KB
-const CHUNK_SIZE: usize = 1024;
-/// Length of a sha256 hash, in bytes.
-const HASH_SIZE: usize = 32;
-/// In case the number of bytes is not a power of two, we fill with zeroes.
-const CHUNK_FILLER: [u8; 32] = [0u8; 32];
+/// In case the number of pieces is not a power of two, we fill with zeroes.
+pub const CHUNK_FILLER: [u8; 32] = [0u8; 32];
 
-
-/// Represents the data structure of a merkle tree.
+/// Represents the data structure of a merkle tree, built with the `H` hashing algorithm.
 /// It includes also the raw file content.
-#[derive(Default, Clone, PartialEq)]
-pub struct FileMerkleTree {
+///
+/// The nodes of the tree are laid out level by level, from the leaves up to the root, so the
+/// node at position `i` of `merkle_tree` is the `i`-th hash of that layout. The pallet stores
+/// the chunks and the nodes individually, keyed by these positions.
+#[derive(DefaultNoBound, CloneNoBound, PartialEqNoBound)]
+pub struct FileMerkleTree<H> {
     pub file_bytes: Vec<u8>,
     pub merkle_tree: Vec<u8>,
     pub pieces: u32,
+    /// Size of the chunks the file is divided in. It is always a power of two.
+    pub chunk_size: u32,
+    pub mode: TreeMode,
+    _hasher: PhantomData<H>,
 }
 
-impl Encode for FileMerkleTree {
-    fn encode(&self) -> Vec<u8> {
-        let file_size = (self.file_bytes.len() as u32).to_le_bytes();
-        let mut result = Vec::from(file_size.as_slice());
-        result.extend_from_slice(self.pieces.to_le_bytes().as_slice());
-        result.extend_from_slice(&self.file_bytes);
-        result.extend_from_slice(&self.merkle_tree);
-        result
+/// Positions of the sibling nodes needed to prove the given piece of a file of `pieces` pieces,
+/// from the leaf level up to the root.
+pub fn sibling_indexes(pieces: u32, piece: u32) -> Vec<u32> {
+    let mut siblings = Vec::new();
+    let mut position = piece;
+    let mut first_index = 0;
+    let mut base = pieces.next_power_of_two();
+    // a single-piece file has its leaf as the merkle root, so the proof is empty
+    while base > 1 {
+        siblings.push(if position % 2 == 0 { position + 1 } else { position - 1 });
+        position = (position - first_index) / 2 + first_index + base;
+        first_index += base;
+        base /= 2;
     }
+    siblings
 }
 
-impl Decode for FileMerkleTree {
-    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
-        let mut buff = [0u8; 4];
-        input.read(&mut buff)?;
-        let file_size = u32::from_le_bytes(buff);
-        input.read(&mut buff)?;
-        let pieces = u32::from_le_bytes(buff);
-        let mut file_bytes = vec![0u8; file_size as usize];
-        input.read(&mut file_bytes)?;
-        let merkle_tree_len = input.remaining_len()?.unwrap();
-        let mut merkle_tree = vec![0u8; merkle_tree_len];
-        input.read(&mut merkle_tree)?;
-        Ok(FileMerkleTree { file_bytes, merkle_tree, pieces })
+/// Positions of the nodes needed to prove the given pieces of a file of `pieces` pieces at once.
+/// The pieces must be sorted and distinct. Siblings that can be computed out of the given pieces
+/// are left out, so every node is listed once, level by level from the leaves up to the root.
+pub fn multi_proof_indexes(pieces: u32, leaves: &[u32]) -> Vec<u32> {
+    let mut nodes = Vec::new();
+    let mut positions = leaves.to_vec();
+    let mut first_index = 0;
+    let mut base = pieces.next_power_of_two();
+    while base > 1 {
+        let mut parents = Vec::with_capacity(positions.len());
+        let mut i = 0;
+        while i < positions.len() {
+            let position = positions[i];
+            if position % 2 == 0 && positions.get(i + 1) == Some(&(position + 1)) {
+                // both children are known, so is their parent
+                i += 1;
+            } else {
+                nodes.push(first_index + if position % 2 == 0 { position + 1 } else { position - 1 });
+            }
+            parents.push(position / 2);
+            i += 1;
+        }
+        positions = parents;
+        first_index += base;
+        base /= 2;
     }
+    nodes
 }
 
-impl TypeInfo for FileMerkleTree {
-    type Identity = Self;
-
-    fn type_info() -> Type {
-        Type::builder().path(Path::new("FileMerkleTree", module_path!())).composite(
-            Fields::named()
-                .field(|f| {
-                    f.ty::<Vec<u8>>()
-                        .name("file_bytes")
-                        .type_name("Vec<u8>")
-                })
-                .field(|f| {
-                    f.ty::<Vec<u8>>()
-                        .name("merkle_tree")
-                        .type_name("Vec<u8>")
-                })
-                .field(|f| {
-                    f.ty::<u32>()
-                        .name("pieces")
-                        .type_name("u32")
-                })
-        )
+/// Builds the whole merkle tree out of its leaves, laid out as described in `FileMerkleTree`.
+pub fn tree_from_leaves<H: FileHasher>(mode: TreeMode, leaves: impl Iterator<Item = Hash>) -> Vec<u8> {
+    let mut tree = leaves.fold(Vec::<u8>::new(), |mut acc, hash| {
+        acc.extend_from_slice(&hash);
+        acc
+    });
+    let pieces = tree.len() / HASH_SIZE;
+    // make the tree a totally balanced binary tree
+    let mut num_items = pieces.next_power_of_two();
+    for _ in 0..(num_items - pieces) {
+        tree.extend_from_slice(&CHUNK_FILLER);
     }
+    let mut pos = 0;
+    while num_items > 1 {
+        for i in (pos..(num_items + pos)).step_by(2) {
+            let slice1 = &tree[(i * HASH_SIZE)..((i + 1) * HASH_SIZE)];
+            let slice2 = &tree[((i + 1) * HASH_SIZE)..((i + 2) * HASH_SIZE)];
+            let hash = mode.hash_node::<H>(slice1, slice2);
+            tree.extend_from_slice(&hash);
+        }
+        pos += num_items;
+        num_items /= 2;
+    }
+    tree
 }
 
-impl EncodeLike for FileMerkleTree {}
+/// Rebuilds the merkle tree out of its leaves in order to find the sibling hashes needed to
+/// prove the given piece, from the leaf level up to the root.
+pub fn siblings_from_leaves<H: FileHasher>(mode: TreeMode, leaves: Vec<Hash>, piece: u32) -> Vec<Hash> {
+    let pieces = leaves.len() as u32;
+    nodes_from_leaves::<H>(mode, leaves, sibling_indexes(pieces, piece))
+}
 
-impl FileMerkleTree {
-    /// Constructs a `FileMerkleTree` out of the provided file bytes.
-    /// It builds the whole merkle tree and keeps file contents.
-    pub fn new(file_bytes: Vec<u8>) -> Self {
-        let chunks = file_bytes.chunks(CHUNK_SIZE);
-        let pieces = chunks.len();
-        let mut tree = chunks.map(|chunk| {
-            if chunk.len() != CHUNK_SIZE {
-                // process last chunk
-                let mut result = vec![0u8; CHUNK_SIZE];
-                for (index, byte) in chunk.iter().enumerate() {
-                    result[index] = *byte;
-                }
-                sha2_256(result.as_slice())
-            } else {
-                sha2_256(&chunk)
-            }
+/// Rebuilds the merkle tree out of its leaves in order to find the nodes at the given positions.
+pub fn nodes_from_leaves<H: FileHasher>(mode: TreeMode, leaves: Vec<Hash>, indexes: Vec<u32>) -> Vec<Hash> {
+    let tree = tree_from_leaves::<H>(mode, leaves.into_iter());
+    indexes
+        .into_iter()
+        .map(|index| {
+            let index = index as usize;
+            let mut hash = Hash::default();
+            hash.copy_from_slice(&tree[index * HASH_SIZE..((index + 1) * HASH_SIZE)]);
+            hash
         })
-            .fold(Vec::<u8>::new(), |mut acc, hash| {
-                acc.append(&mut hash.to_vec());
-                acc
-            });
-        // make the tree a totally balanced binary tree
-        let mut num_items = pieces.next_power_of_two();
-        for _ in 0..(num_items - pieces) {
-            tree.extend_from_slice(&CHUNK_FILLER);
-        }
-        let mut pos = 0;
-        while num_items > 1 {
-            for i in (pos..(num_items + pos)).step_by(2) {
-                let slice1 = &tree[(i * HASH_SIZE)..((i + 1) * HASH_SIZE)];
-                let slice2 = &tree[((i + 1) * HASH_SIZE)..((i + 2) * HASH_SIZE)];
-                let mut result = Vec::with_capacity(HASH_SIZE * 2);
-                result.extend_from_slice(slice1);
-                result.extend_from_slice(slice2);
-                let hash = sha2_256(&result.as_slice());
-                tree.extend_from_slice(&hash);
-            }
-            pos += num_items;
-            num_items /= 2;
+        .collect()
+}
+
+impl<H: FileHasher> FileMerkleTree<H> {
+    /// Constructs a `FileMerkleTree` out of the provided file bytes, divided in chunks of
+    /// `chunk_size` bytes.
+    /// It builds the whole merkle tree and keeps file contents.
+    ///
+    /// Returns `None` if the file is empty.
+    pub fn new(file_bytes: Vec<u8>, chunk_size: u32) -> Option<Self> {
+        Self::build(file_bytes, chunk_size, TreeMode::Standard)
+    }
+
+    /// Constructs a Bittorrent v2 compatible `FileMerkleTree` out of the provided file bytes,
+    /// following the [BEP 52](https://www.bittorrent.org/beps/bep_0052.html) rules.
+    /// Its merkle root is the `pieces root` of the file.
+    ///
+    /// Returns `None` if the file is empty.
+    pub fn new_bep52(file_bytes: Vec<u8>) -> Option<Self> {
+        Self::build(file_bytes, BEP52_BLOCK_SIZE, TreeMode::Bep52)
+    }
+
+    fn build(file_bytes: Vec<u8>, chunk_size: u32, mode: TreeMode) -> Option<Self> {
+        if file_bytes.is_empty() {
+            return None;
         }
-        Self {
+        let chunks = file_bytes.chunks(chunk_size as usize);
+        let pieces = chunks.len();
+        let tree = tree_from_leaves::<H>(mode, chunks.map(|chunk| mode.hash_leaf::<H>(chunk, chunk_size)));
+        Some(Self {
             file_bytes,
             pieces: pieces as u32,
             merkle_tree: tree,
-        }
-    }
-
-    fn file_chunk_at(&self, position: u32) -> &[u8] {
-        let pos = position as usize * CHUNK_SIZE;
-        let limit = if position == (self.pieces - 1) {
-            self.file_bytes.len()
-        } else { pos + CHUNK_SIZE };
-        &self.file_bytes[pos..limit]
+            chunk_size,
+            mode,
+            _hasher: PhantomData,
+        })
     }
 
-    /// Returns the merkle root of this file.
-    /// The merkle root is stored as the last 32 bytes of the `merkle_tree` array.
-    pub fn merkle_root(&self) -> &[u8] {
-        &self.merkle_tree[self.merkle_tree.len() - HASH_SIZE..]
+    /// The chunks of the file, in order. The last one may be shorter than `chunk_size`.
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        self.file_bytes.chunks(self.chunk_size as usize)
     }
 
-    fn find_proof(&self, position: usize, first_index: usize, base: usize, proof: &mut Vec<Vec<u8>>) {
-        if base == 1 {
-            // we do not need to return the merkle root
-            return;
-        }
-        let sibling = if position % 2 == 0 { position + 1 } else { position - 1 };
-        let parent = (position - first_index) / 2 + first_index + base;
-        let hash = self.merkle_tree[sibling * HASH_SIZE..((sibling + 1) * HASH_SIZE)].to_vec();
-        proof.push(hash);
-        self.find_proof(parent, first_index + base,base / 2, proof);
+    /// The nodes of the merkle tree, in the order described by `FileMerkleTree`.
+    pub fn nodes(&self) -> impl Iterator<Item = Hash> + '_ {
+        self.merkle_tree.chunks(HASH_SIZE).map(|node| {
+            let mut hash = Hash::default();
+            hash.copy_from_slice(node);
+            hash
+        })
     }
 
-    /// Finds the content and merkle proof of a given piece
-    /// The piece is identified by its position.
+    /// Builds a proof of the chunks at the given positions, with every node needed to rebuild the
+    /// root listed once. The positions are sorted and deduplicated.
     ///
-    /// Returns a tuple with the given chunk content and the merkle proof.
-    /// The sha256 of the content can be used to compute the merkle root hash
-    /// along with the merkle proof.
-    pub fn merkle_proof(&self, piece: u32) -> Option<(Vec<u8>, Vec<Vec<u8>>)> {
-        if piece >= self.pieces {
+    /// Returns `None` if no position is given, or if some of them are out of the file.
+    pub fn multi_proof(&self, indices: &[u32]) -> Option<MultiProof> {
+        let mut leaf_indices = indices.to_vec();
+        leaf_indices.sort_unstable();
+        leaf_indices.dedup();
+        if leaf_indices.last().map_or(true, |last| *last >= self.pieces) {
             return None;
         }
-        let content = self.file_chunk_at(piece).to_vec();
-        let proof: Vec<Vec<u8>> = if self.pieces == 1 {
-            vec![self.file_chunk_at(piece).to_vec()]
-        } else {
-            let mut proof = Vec::new();
-            self.find_proof(piece as usize, 0, self.pieces.next_power_of_two() as usize, &mut proof);
-            proof
-        };
-        Some((content, proof))
+        let nodes = self.nodes().collect::<Vec<Hash>>();
+        let hashes = multi_proof_indexes(self.pieces, &leaf_indices)
+            .into_iter()
+            .map(|index| nodes[index as usize])
+            .collect();
+        let contents = leaf_indices.iter()
+            .map(|index| self.chunks().nth(*index as usize).map(|chunk| chunk.to_vec()))
+            .collect::<Option<Vec<Vec<u8>>>>()?;
+        Some(MultiProof {
+            leaf_indices,
+            pieces: self.pieces,
+            chunk_size: self.chunk_size,
+            mode: self.mode,
+            hashes,
+            contents,
+        })
+    }
+
+    /// Returns the merkle root of this file.
+    /// The merkle root is stored as the last 32 bytes of the `merkle_tree` array.
+    pub fn merkle_root(&self) -> &[u8] {
+        &self.merkle_tree[self.merkle_tree.len() - HASH_SIZE..]
     }
-}
\ No newline at end of file
+}
@@ -9,10 +9,16 @@
 //!
 //! This methodology is pretty similar to how the Bittorrent protocol works. More information
 //! about this protocol can be found [here](https://www.bittorrent.org/beps/bep_0030.html).
+//! Files uploaded in `TreeMode::Bep52` follow the
+//! [BEP 52](https://www.bittorrent.org/beps/bep_0052.html) rules, so their merkle root is the
+//! same `pieces root` Bittorrent v2 clients compute.
 //!
 //! This implementation has the following characteristics:
-//! - It uses a fixed file chunk of 1KB
-//! - The hashing algorithm is `sha256` instead of Bittorrent's original one: `sha1`.
+//! - Files are divided in chunks of `2^n` bytes, where `n` is chosen on upload within the bounds
+//! set by the pallet configuration.
+//! - The hashing algorithm is set by the pallet configuration through the `FileHasher` type.
+//! `sha256`, `blake2b-256` and `keccak256` are supported, instead of Bittorrent's original one:
+//! `sha1`.
 //! - Files are stored on the blockchain. Bear in mind that this is an overall bad practice and
 //! should be used with caution. A notable improvement to this pallet would actually be to
 //! use IPFS or any other decentralized storage protocol to store files, and only keep on the
@@ -24,98 +30,1814 @@ extern crate core;
 
 mod file_merkle_tree;
 
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod migrations;
+pub mod weights;
+pub use weights::*;
+
 // Re-export pallet items so that they can be accessed from the crate namespace.
 pub use pallet::*;
+pub use pallet_template_merkle_proof::{Blake2_256, FileError, FileHasher, Keccak256, MerkleProof, MultiProof, Sha2_256, TreeMode};
+
+/// Maximum number of files returned by every page of `get_files_paged`.
+pub const MAX_FILES_PER_PAGE: u32 = 1000;
 
 #[frame_support::pallet]
 pub mod pallet {
-    use frame_support::pallet_prelude::*;
+    use frame_support::{
+        pallet_prelude::*,
+        traits::{Currency, Randomness, ReservableCurrency},
+    };
     use frame_system::pallet_prelude::*;
-    use sp_std::vec::Vec;
+    use frame_support::traits::BalanceStatus;
+    use sp_runtime::traits::{Saturating, StaticLookup, TrailingZeroInput, Zero};
+    use sp_std::{vec, vec::Vec};
     use crate::file_merkle_tree::FileMerkleTree;
+    use crate::weights::WeightInfo;
+    use codec::DecodeAll;
+    use crate::file_merkle_tree::{multi_proof_indexes, nodes_from_leaves, sibling_indexes, siblings_from_leaves, CHUNK_FILLER};
+    use pallet_template_merkle_proof::{
+        FileError, FileHasher, FileInfo, Hash, MerkleProof, MultiProof, TreeMode, UploadedFile, BEP52_BLOCK_SIZE, HASH_SIZE, MAX_CHUNK_SIZE_EXPONENT,
+    };
+
+    pub(super) type BalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    type AccountIdLookupOf<T> = <<T as frame_system::Config>::Lookup as StaticLookup>::Source;
+
+    /// Merkle tree of a file, built with the configured hashing algorithm.
+    pub type FileMerkleTreeOf<T> = FileMerkleTree<<T as Config>::FileHasher>;
+
+    /// Biggest chunk files can be divided in, in bytes.
+    pub struct MaxChunkSize<T>(PhantomData<T>);
+
+    impl<T: Config> Get<u32> for MaxChunkSize<T> {
+        fn get() -> u32 {
+            1 << T::MaxChunkSizeExponent::get()
+        }
+    }
+
+    /// Most sibling hashes the merkle proof of a chunk can have, for a file of `MaxPieces` pieces.
+    pub struct MaxProofDepth<T>(PhantomData<T>);
+
+    impl<T: Config> Get<u32> for MaxProofDepth<T> {
+        fn get() -> u32 {
+            MerkleProof::depth(T::MaxPieces::get()) as u32
+        }
+    }
+
+    /// What to do when a file that is already stored gets uploaded again.
+    #[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum DuplicatePolicy {
+        /// The upload fails with `FileAlreadyExists`.
+        Reject,
+        /// The uploader becomes a co-owner of the file. The file is kept until every owner
+        /// removes it.
+        Share,
+    }
+
+    /// Which merkle tree nodes of a file are kept in `TreeNodes`. Proofs are served from the
+    /// stored nodes, and the missing ones are computed again on demand: storing less nodes takes
+    /// less state, at the cost of more reads and hashing for every proof.
+    #[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum TreeStorage {
+        /// Every node is stored, except the ones of the zero-filled padding leaves. Proofs only
+        /// read their sibling hashes.
+        Full,
+        /// Only the leaves are stored. Proofs read all of them and rebuild the tree.
+        Leaves,
+        /// No node is stored, only the chunks and the merkle root. Proofs read and hash every
+        /// chunk and rebuild the tree.
+        RootOnly,
+        /// Neither the chunks nor the nodes are stored, only the merkle root. The file bytes are
+        /// kept by the nodes in the indexed block body of its `store_indexed` extrinsic, and
+        /// proofs are built from them.
+        Indexed,
+    }
+
+    impl TreeStorage {
+        /// Whether the node at position `index` of the tree of a file of `pieces` pieces is
+        /// stored.
+        pub fn stores_node(&self, index: u32, pieces: u32) -> bool {
+            match self {
+                TreeStorage::Full => index < pieces || index >= pieces.next_power_of_two(),
+                TreeStorage::Leaves => index < pieces,
+                TreeStorage::RootOnly | TreeStorage::Indexed => false,
+            }
+        }
+
+        /// Whether the chunks of the file are stored in `Chunks`.
+        pub fn stores_chunks(&self) -> bool {
+            *self != TreeStorage::Indexed
+        }
+
+        /// Number of nodes stored for a file of `pieces` pieces.
+        pub fn stored_nodes(&self, pieces: u32) -> u32 {
+            match self {
+                TreeStorage::Full => pieces.next_power_of_two() + pieces - 1,
+                TreeStorage::Leaves => pieces,
+                TreeStorage::RootOnly | TreeStorage::Indexed => 0,
+            }
+        }
+    }
+
+    /// Longest `clear_prefix` cursor kept between two deletions, in bytes. It is the storage key
+    /// of a chunk or a merkle tree node, which takes 92 bytes with 32-byte hashes.
+    pub const MAX_DELETION_CURSOR_LENGTH: u32 = 128;
+
+    /// Where the deletion of the chunks and merkle tree nodes of a removed file resumes from.
+    #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum DeletionCursor {
+        /// The chunks are being deleted, from the given `clear_prefix` cursor if any.
+        Chunks(Option<BoundedVec<u8, ConstU32<MAX_DELETION_CURSOR_LENGTH>>>),
+        /// The chunks are gone, and the merkle tree nodes are being deleted.
+        TreeNodes(Option<BoundedVec<u8, ConstU32<MAX_DELETION_CURSOR_LENGTH>>>),
+    }
+
+    /// A file stored by this pallet, along with the accounts holding it. Its chunks and merkle
+    /// tree nodes are kept apart, in `Chunks` and `TreeNodes`.
+    #[derive(CloneNoBound, PartialEqNoBound, Encode, Decode, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct StoredFile<T: Config> {
+        /// Owners of the file, along with the deposit each of them reserved, in upload order.
+        pub owners: BoundedVec<(T::AccountId, BalanceOf<T>), T::MaxOwners>,
+        /// Accounts allowed to edit the metadata of the file and to remove it on behalf of an
+        /// owner, along with the owner that granted them.
+        pub managers: BoundedVec<(T::AccountId, T::AccountId), T::MaxManagers>,
+        /// Size of the file, in bytes.
+        pub size: u32,
+        pub pieces: u32,
+        /// Size of the chunks the file is divided in. It is always a power of two.
+        pub chunk_size: u32,
+        pub mode: TreeMode,
+        /// Which merkle tree nodes are kept in `TreeNodes`.
+        pub tree_storage: TreeStorage,
+        /// Block the file was uploaded at.
+        pub uploaded_at: T::BlockNumber,
+        /// Name of the file, set by its owners with `set_file_metadata`.
+        pub name: BoundedVec<u8, T::MaxNameLength>,
+        /// MIME type of the file, set by its owners with `set_file_metadata`.
+        pub content_type: BoundedVec<u8, T::MaxContentTypeLength>,
+        pub description: Option<BoundedVec<u8, T::MaxDescriptionLength>>,
+        /// Block at which the file is removed, unless renewed. Files uploaded without a retention
+        /// period never expire.
+        pub expires_at: Option<T::BlockNumber>,
+    }
+
+    /// Identifier of a multi-part upload session.
+    pub type SessionId = u64;
+
+    /// A multi-part upload in progress. Its parts are kept in `UploadParts` until the session is
+    /// finalized or goes stale.
+    #[derive(CloneNoBound, PartialEqNoBound, Encode, Decode, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct UploadSession<T: Config> {
+        pub owner: T::AccountId,
+        /// Size of the whole file, in bytes.
+        pub total_size: u32,
+        /// Merkle root the assembled file must have.
+        pub expected_root: T::Hash,
+        pub chunk_size_exponent: u8,
+        pub mode: TreeMode,
+        /// Amount reserved from the owner while the parts are stored.
+        pub deposit: BalanceOf<T>,
+        /// Number of blocks the file is kept once finalized, if it is not kept forever.
+        pub retention: Option<T::BlockNumber>,
+        /// Block at which the session is aborted if it has not been finalized.
+        pub expires_at: T::BlockNumber,
+    }
+
+    /// Identifier of a proof-of-storage challenge.
+    pub type ChallengeId = u64;
+
+    /// An account that declared it holds some stored files, and bonded funds as a guarantee that
+    /// it answers the challenges about them.
+    #[derive(CloneNoBound, PartialEqNoBound, Encode, Decode, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct StorageProvider<T: Config> {
+        /// Amount reserved from the provider, slashed if it misses a challenge.
+        pub bond: BalanceOf<T>,
+        /// Merkle roots of the files the provider holds.
+        pub files: BoundedVec<T::Hash, T::MaxProviderFiles>,
+        /// Number of challenges the provider has not answered yet.
+        pub open_challenges: u32,
+    }
+
+    /// A request for a storage provider to prove it holds a chunk of a file.
+    #[derive(CloneNoBound, PartialEqNoBound, Encode, Decode, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct Challenge<T: Config> {
+        pub merkle_root: T::Hash,
+        /// Position of the chunk to prove.
+        pub chunk_index: u32,
+        /// Number of pieces of the file when the challenge was issued.
+        pub pieces: u32,
+        /// Size of the chunks of the file.
+        pub chunk_size: u32,
+        /// Rules the merkle tree of the file was built with.
+        pub mode: TreeMode,
+        /// Block at which the provider is slashed if it has not answered.
+        pub deadline: T::BlockNumber,
+    }
+
+    /// The current storage version.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
     #[pallet::pallet]
     #[pallet::generate_store(pub (super) trait Store)]
-    #[pallet::without_storage_info]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     #[pallet::config]
     pub trait Config: frame_system::Config {
         /// Because this pallet emits events, it depends on the runtime's definition of an event.
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+        /// Minimum exponent `n` of the `2^n` bytes chunks files can be divided in.
+        #[pallet::constant]
+        type MinChunkSizeExponent: Get<u8>;
+
+        /// Maximum exponent `n` of the `2^n` bytes chunks files can be divided in.
+        #[pallet::constant]
+        type MaxChunkSizeExponent: Get<u8>;
+
+        /// Hashing algorithm used to build the merkle tree of the files.
+        type FileHasher: FileHasher + 'static;
+
+        /// Maximum size of the files, in bytes.
+        #[pallet::constant]
+        type MaxFileSize: Get<u32>;
+
+        /// Maximum number of chunks a file can be divided in. Uploads write one chunk per piece,
+        /// so it must be low enough for the heaviest upload of `MaxFileSize` bytes to fit in a
+        /// block, which `integrity_test` checks.
+        #[pallet::constant]
+        type MaxPieces: Get<u32>;
+
+        /// Whether uploading a file that is already stored fails or makes the uploader a
+        /// co-owner of it.
+        #[pallet::constant]
+        type DuplicatePolicy: Get<DuplicatePolicy>;
+
+        /// Which merkle tree nodes are stored for the files uploaded from now on.
+        #[pallet::constant]
+        type TreeStorage: Get<TreeStorage>;
+
+        /// Maximum number of co-owners a file can have under `DuplicatePolicy::Share`.
+        #[pallet::constant]
+        type MaxOwners: Get<u32>;
+
+        /// Maximum number of managers a file can have, counting the ones of all its owners.
+        #[pallet::constant]
+        type MaxManagers: Get<u32>;
+
+        /// Size of the parts files are sent in by multi-part uploads, in bytes. Every part but
+        /// the last one must have exactly this size.
+        #[pallet::constant]
+        type MaxPartSize: Get<u32>;
+
+        /// Number of blocks a multi-part upload session can stay open before it is aborted.
+        #[pallet::constant]
+        type UploadTimeout: Get<Self::BlockNumber>;
+
+        /// Maximum number of files that can expire at the same block. Upload sessions are bounded
+        /// the same way, on their own.
+        #[pallet::constant]
+        type MaxExpiriesPerBlock: Get<u32>;
+
+        /// Maximum number of blocks the expiry of a file or an upload session is delayed by when
+        /// `MaxExpiriesPerBlock` of them already expire at the block it should expire at.
+        #[pallet::constant]
+        type MaxExpiryDelay: Get<u32>;
+
+        /// Maximum length of the file names, in bytes.
+        #[pallet::constant]
+        type MaxNameLength: Get<u32>;
+
+        /// Maximum length of the file MIME types, in bytes.
+        #[pallet::constant]
+        type MaxContentTypeLength: Get<u32>;
+
+        /// Maximum length of the file descriptions, in bytes.
+        #[pallet::constant]
+        type MaxDescriptionLength: Get<u32>;
+
+        /// Currency used to reserve the storage deposits of the uploaded files.
+        type Currency: ReservableCurrency<Self::AccountId>;
+
+        /// Base amount reserved for every uploaded file.
+        #[pallet::constant]
+        type DepositBase: Get<BalanceOf<Self>>;
+
+        /// Amount reserved for every byte stored, counting both the file and its merkle tree.
+        #[pallet::constant]
+        type DepositPerByte: Get<BalanceOf<Self>>;
+
+        /// Source of randomness the proof-of-storage challenges are picked with.
+        type Randomness: Randomness<Self::Hash, Self::BlockNumber>;
+
+        /// Amount reserved from the storage providers, slashed when they miss a challenge.
+        #[pallet::constant]
+        type ProviderBond: Get<BalanceOf<Self>>;
+
+        /// Maximum number of registered storage providers.
+        #[pallet::constant]
+        type MaxProviders: Get<u32>;
+
+        /// Maximum number of files a storage provider can declare it holds.
+        #[pallet::constant]
+        type MaxProviderFiles: Get<u32>;
+
+        /// Number of challenges issued every block.
+        #[pallet::constant]
+        type ChallengesPerBlock: Get<u32>;
+
+        /// Number of blocks a storage provider has to answer a challenge.
+        #[pallet::constant]
+        type ChallengePeriod: Get<Self::BlockNumber>;
+
+        /// Weight information for extrinsics in this pallet.
+        type WeightInfo: WeightInfo;
     }
 
 
     #[pallet::event]
     #[pallet::generate_deposit(pub (super) fn deposit_event)]
     pub enum Event<T: Config> {
-        /// Event emitted when a claim has been created.
-        FileUploaded { who: T::AccountId, merkle_root: T::Hash, pieces: u32 },
+        /// Event emitted when a claim has been created. `shared` is true when the file was
+        /// already stored and the uploader became one of its co-owners.
+        FileUploaded { who: T::AccountId, merkle_root: T::Hash, size: u32, pieces: u32, shared: bool },
+        /// Event emitted when the owner of a file has set its metadata.
+        FileMetadataSet { who: T::AccountId, merkle_root: T::Hash },
+        /// Event emitted when an owner has handed its share of a file, deposit included, to
+        /// another account.
+        OwnershipTransferred { merkle_root: T::Hash, from: T::AccountId, to: T::AccountId },
+        /// Event emitted when an owner has granted manager rights over a file to an account.
+        ManagerAdded { merkle_root: T::Hash, owner: T::AccountId, manager: T::AccountId },
+        /// Event emitted when an owner has revoked the manager rights it granted to an account.
+        ManagerRemoved { merkle_root: T::Hash, owner: T::AccountId, manager: T::AccountId },
+        /// Event emitted when a file has been removed by its last owner. Its chunks and merkle
+        /// tree are deleted afterwards, as blocks have room for it.
+        FileRemoved { who: T::AccountId, merkle_root: T::Hash },
+        /// Event emitted when a co-owner gave up a file that other accounts still own.
+        OwnerRemoved { who: T::AccountId, merkle_root: T::Hash },
+        /// Event emitted when the retention period of a file has ended, and it has been removed
+        /// from all of its owners.
+        FileExpired { merkle_root: T::Hash },
+        /// Event emitted when the retention period of a file has been extended.
+        FileRenewed { who: T::AccountId, merkle_root: T::Hash, expires_at: T::BlockNumber },
+        /// Event emitted when a multi-part upload session has been opened.
+        UploadStarted { who: T::AccountId, session_id: SessionId, expected_root: T::Hash },
+        /// Event emitted when a multi-part upload session went stale and its parts were dropped.
+        UploadAborted { who: T::AccountId, session_id: SessionId },
+        /// Event emitted when an account has bonded funds to become a storage provider.
+        ProviderRegistered { provider: T::AccountId },
+        /// Event emitted when a storage provider has left, getting its bond back.
+        ProviderUnregistered { provider: T::AccountId },
+        /// Event emitted when a storage provider has declared it holds a file.
+        FileDeclared { provider: T::AccountId, merkle_root: T::Hash },
+        /// Event emitted when a storage provider has declared it no longer holds a file.
+        FileUndeclared { provider: T::AccountId, merkle_root: T::Hash },
+        /// Event emitted when a storage provider has been challenged to prove it holds a chunk
+        /// of a file before the `deadline` block.
+        ChallengeIssued {
+            challenge_id: ChallengeId,
+            provider: T::AccountId,
+            merkle_root: T::Hash,
+            chunk_index: u32,
+            deadline: T::BlockNumber,
+        },
+        /// Event emitted when a storage provider has answered a challenge with a valid proof.
+        ChallengeAnswered { challenge_id: ChallengeId, provider: T::AccountId },
+        /// Event emitted when a storage provider missed a challenge. Its bond has been slashed
+        /// and it is no longer registered.
+        ProviderSlashed { challenge_id: ChallengeId, provider: T::AccountId, amount: BalanceOf<T> },
     }
 
     #[pallet::error]
     pub enum Error<T> {
         /// Could not obtain the merkle root hash
         Unhasheable,
+        /// The chunk size exponent is out of the configured bounds
+        InvalidChunkSize,
+        /// There is no file with the given merkle root
+        FileNotFound,
+        /// Only the owner of the file can perform this operation
+        NotOwner,
+        /// The file is bigger than `MaxFileSize`
+        FileTooLarge,
+        /// Empty files cannot be uploaded
+        EmptyFile,
+        /// The file is already stored and `DuplicatePolicy` is `Reject`
+        FileAlreadyExists,
+        /// The uploader already owns the file
+        AlreadyOwner,
+        /// The file has reached `MaxOwners` co-owners
+        TooManyOwners,
+        /// There is no upload session with the given id
+        SessionNotFound,
+        /// The part index is out of the file, or the part does not have the expected size
+        InvalidPart,
+        /// Some parts of the file have not been uploaded yet
+        MissingParts,
+        /// The merkle root of the assembled file is not the expected one
+        RootMismatch,
+        /// The file name, MIME type or description is too long
+        MetadataTooLong,
+        /// Files can only be indexed from within an extrinsic
+        BadContext,
+        /// Retention periods must last at least one block
+        InvalidRetention,
+        /// `MaxExpiriesPerBlock` files or upload sessions already expire at every block up to
+        /// `MaxExpiryDelay` blocks after the requested one
+        TooManyExpiries,
+        /// The file has no retention period, so it never expires
+        NotExpiring,
+        /// The account is already a manager of the file
+        AlreadyManager,
+        /// The account is not a manager of the file
+        NotManager,
+        /// The file has reached `MaxManagers` managers
+        TooManyManagers,
+        /// The account is already a storage provider
+        AlreadyProvider,
+        /// The account is not a storage provider
+        NotProvider,
+        /// There are already `MaxProviders` storage providers
+        TooManyProviders,
+        /// The storage provider already declared the file
+        AlreadyDeclared,
+        /// The storage provider did not declare the file
+        NotDeclared,
+        /// The storage provider has declared `MaxProviderFiles` files
+        TooManyDeclaredFiles,
+        /// The storage provider still has challenges to answer
+        OpenChallenges,
+        /// There is no open challenge with the given id for the caller
+        ChallengeNotFound,
+        /// The merkle proof does not prove the challenged chunk
+        InvalidProof,
+        /// The size or number of pieces given does not match the upload session
+        WrongWitness,
+        /// The file would be divided in more than `MaxPieces` chunks
+        TooManyPieces,
+        /// Only indexed files, whose bytes are not kept in state, can be declared by storage
+        /// providers
+        NotIndexed,
+        /// The stored files are being migrated to a new layout, no file can be stored until it
+        /// is done
+        MigrationOngoing,
     }
 
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            let mut weight = T::DbWeight::get().reads(1);
+            for session_id in SessionsExpiring::<T>::take(now) {
+                let parts = Self::abort_session(session_id);
+                weight.saturating_accrue(T::WeightInfo::abort_upload(parts));
+            }
+            weight.saturating_accrue(T::DbWeight::get().reads(1));
+            for merkle_root in FilesExpiring::<T>::take(now) {
+                Self::expire_file(merkle_root);
+                weight.saturating_accrue(T::WeightInfo::expire_file());
+            }
+            weight.saturating_accrue(T::DbWeight::get().reads(1));
+            for (challenge_id, provider) in ChallengesExpiring::<T>::drain_prefix(now) {
+                Self::expire_challenge(provider, challenge_id);
+                weight.saturating_accrue(T::WeightInfo::expire_challenge());
+            }
+            for index in 0..T::ChallengesPerBlock::get() {
+                Self::issue_challenge(now, index);
+                weight.saturating_accrue(T::WeightInfo::issue_challenge());
+            }
+            weight
+        }
+
+        fn on_idle(_now: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            let mut used_weight = crate::migrations::v1::migrate_files::<T>(remaining_weight);
+            let base_weight = T::WeightInfo::delete_file_items(0);
+            let item_weight = T::WeightInfo::delete_file_items(1).saturating_sub(base_weight);
+            loop {
+                let available = remaining_weight.saturating_sub(used_weight).saturating_sub(base_weight);
+                let limit = available.ref_time().checked_div(item_weight.ref_time()).unwrap_or(0);
+                if limit == 0 {
+                    break;
+                }
+                let merkle_root = match PendingDeletions::<T>::iter_keys().next() {
+                    Some(merkle_root) => merkle_root,
+                    None => break,
+                };
+                let removed = Self::delete_file_items(merkle_root, limit.min(u32::MAX as u64) as u32);
+                used_weight.saturating_accrue(T::WeightInfo::delete_file_items(removed));
+            }
+            used_weight
+        }
+
+        fn integrity_test() {
+            assert!(T::MinChunkSizeExponent::get() <= T::MaxChunkSizeExponent::get());
+            assert!(T::MaxChunkSizeExponent::get() <= MAX_CHUNK_SIZE_EXPONENT);
+            assert!(T::MaxOwners::get() >= 1);
+            assert!(T::MaxManagers::get() >= 1);
+            assert!(T::MaxPartSize::get() >= 1);
+            assert!(T::TreeStorage::get().stores_chunks(), "only `store_indexed` can index files");
+            assert!(!T::ChallengePeriod::get().is_zero());
+            assert!(T::MaxExpiriesPerBlock::get() >= 1);
+            assert!(T::MaxPieces::get() >= 1);
+            let block_weights = T::BlockWeights::get();
+            let max_extrinsic = block_weights.get(DispatchClass::Normal).max_extrinsic.unwrap_or(block_weights.max_block);
+            let (size, pieces) = (T::MaxFileSize::get(), T::MaxPieces::get());
+            assert!(
+                T::WeightInfo::upload_file(size, pieces).all_lte(max_extrinsic) &&
+                    T::WeightInfo::store_indexed(size, pieces).all_lte(max_extrinsic) &&
+                    T::WeightInfo::finalize_upload(size, pieces).all_lte(max_extrinsic),
+                "the heaviest upload must fit in a block, lower `MaxPieces` or `MaxFileSize`"
+            );
+        }
+    }
+
+    #[pallet::storage]
+    pub(super) type Files<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, StoredFile<T>, OptionQuery>;
+
+    /// Merkle roots of the files owned by every account.
+    #[pallet::storage]
+    pub(super) type FilesByOwner<T: Config> = StorageDoubleMap<
+        _, Blake2_128Concat, T::AccountId, Blake2_128Concat, T::Hash, (), OptionQuery
+    >;
+
+    /// Chunks of the stored files, by merkle root and position.
+    #[pallet::storage]
+    pub(super) type Chunks<T: Config> = StorageDoubleMap<
+        _, Blake2_128Concat, T::Hash, Twox64Concat, u32, BoundedVec<u8, MaxChunkSize<T>>, OptionQuery
+    >;
+
+    /// Merkle tree nodes of the stored files, by merkle root and position in the tree layout
+    /// described by `FileMerkleTree`.
+    #[pallet::storage]
+    pub(super) type TreeNodes<T: Config> = StorageDoubleMap<
+        _, Blake2_128Concat, T::Hash, Twox64Concat, u32, Hash, OptionQuery
+    >;
+
+    /// Merkle roots of the files with a retention period, by the block they expire at.
+    #[pallet::storage]
+    pub(super) type FilesExpiring<T: Config> = StorageMap<
+        _, Twox64Concat, T::BlockNumber, BoundedVec<T::Hash, T::MaxExpiriesPerBlock>, ValueQuery
+    >;
+
+    /// Removed files whose chunks and merkle tree nodes are still to be deleted, with the point
+    /// the deletion resumes from.
+    #[pallet::storage]
+    pub(super) type PendingDeletions<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, DeletionCursor, OptionQuery>;
+
+    /// Progress of the migration of the stored files to the v1 layout, while it is ongoing.
+    #[pallet::storage]
+    pub(super) type FilesMigration<T: Config> = StorageValue<_, crate::migrations::v1::MigrationProgress<T::Hash>, OptionQuery>;
+
+    #[pallet::storage]
+    pub(super) type NextSessionId<T: Config> = StorageValue<_, SessionId, ValueQuery>;
+
+    #[pallet::storage]
+    pub(super) type UploadSessions<T: Config> = StorageMap<_, Twox64Concat, SessionId, UploadSession<T>, OptionQuery>;
+
+    /// Parts of the multi-part uploads in progress, by session and part index.
+    #[pallet::storage]
+    pub(super) type UploadParts<T: Config> = StorageDoubleMap<
+        _, Twox64Concat, SessionId, Twox64Concat, u32, BoundedVec<u8, T::MaxPartSize>, OptionQuery
+    >;
+
+    /// Upload sessions by the block they expire at.
     #[pallet::storage]
-    pub(super) type Files<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, FileMerkleTree), OptionQuery>;
+    pub(super) type SessionsExpiring<T: Config> = StorageMap<
+        _, Twox64Concat, T::BlockNumber, BoundedVec<SessionId, T::MaxExpiriesPerBlock>, ValueQuery
+    >;
+
+    /// Registered storage providers.
+    #[pallet::storage]
+    pub(super) type Providers<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, StorageProvider<T>, OptionQuery>;
+
+    /// Accounts of the registered storage providers, which challenges are picked from.
+    #[pallet::storage]
+    pub(super) type ProviderAccounts<T: Config> = StorageValue<_, BoundedVec<T::AccountId, T::MaxProviders>, ValueQuery>;
+
+    #[pallet::storage]
+    pub(super) type NextChallengeId<T: Config> = StorageValue<_, ChallengeId, ValueQuery>;
+
+    /// Open proof-of-storage challenges, by storage provider and id.
+    #[pallet::storage]
+    pub(super) type Challenges<T: Config> = StorageDoubleMap<
+        _, Blake2_128Concat, T::AccountId, Twox64Concat, ChallengeId, Challenge<T>, OptionQuery
+    >;
+
+    /// Open challenges by the block they expire at, along with their storage provider.
+    #[pallet::storage]
+    pub(super) type ChallengesExpiring<T: Config> = StorageDoubleMap<
+        _, Twox64Concat, T::BlockNumber, Twox64Concat, ChallengeId, T::AccountId, OptionQuery
+    >;
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         /// Uploads a file to the blockchain and computes its merkle tree.
+        /// A deposit proportional to the stored bytes is reserved from the uploader until the file
+        /// is removed. See `DepositBase` and `DepositPerByte`.
+        /// Uploading a file that is already stored either fails or adds the uploader to its
+        /// co-owners, who reserve the same deposit, depending on `DuplicatePolicy`.
+        /// The file can take up to `MaxFileSize` bytes, and is divided in chunks of
+        /// `2^chunk_size_exponent` bytes, which must be within the `MinChunkSizeExponent` and
+        /// `MaxChunkSizeExponent` bounds.
+        ///
+        /// With `TreeMode::Bep52` the merkle tree is compatible with Bittorrent v2, and the
+        /// merkle root is the `pieces root` of the file. BEP 52 blocks are always 16KiB, so the
+        /// chunk size exponent must be 14.
+        ///
+        /// With a `retention` period, the file is removed once that many blocks have passed,
+        /// unless it is renewed with `renew_file`. Otherwise it is kept until its owners remove it.
+        ///
         /// Bear in mind that as a general rule of thumb blockchains should not store big amounts of
         /// data, and instead decentralized services like IPFS should be used, storing only the
         /// associated hash on the blockchain.
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::upload_file(
+            file_bytes.len() as u32,
+            Pallet::<T>::pieces_for(file_bytes.len(), *chunk_size_exponent),
+        ))]
         #[pallet::call_index(0)]
-        pub fn upload_file(origin: OriginFor<T>, file_bytes: Vec<u8>) -> DispatchResult {
+        pub fn upload_file(
+            origin: OriginFor<T>,
+            file_bytes: Vec<u8>,
+            chunk_size_exponent: u8,
+            mode: TreeMode,
+            retention: Option<T::BlockNumber>,
+        ) -> DispatchResult {
             // Check that the extrinsic was signed and get the signer.
             // This function will return an error if the extrinsic is not signed.
             let who = ensure_signed(origin)?;
 
-            let file_merkle_tree = FileMerkleTree::new(file_bytes.clone());
+            let file_merkle_tree = Self::build_tree(file_bytes, chunk_size_exponent, mode)?;
             let merkle_root = T::Hash::decode(
                 &mut file_merkle_tree.merkle_root()
             ).or(Err(Error::<T>::Unhasheable))?;
 
-            // Store the claim with the sender and block number.
-            Files::<T>::insert(&merkle_root, (&who, &file_merkle_tree));
+            Self::store_file(who, merkle_root, file_merkle_tree, T::TreeStorage::get(), retention)
+        }
 
-            // Emit an event that the claim was created.
-            Self::deposit_event(Event::FileUploaded {
-                who,
+        /// Removes a file, along with its merkle tree, from the blockchain storage.
+        /// Only the owners of the file, or their managers on their behalf, are allowed to remove
+        /// it, and the owner gets its deposit back.
+        /// A file with several co-owners is only removed from the storage once the last of them
+        /// removes it.
+        #[pallet::weight(T::WeightInfo::remove_file())]
+        #[pallet::call_index(1)]
+        pub fn remove_file(origin: OriginFor<T>, merkle_root: T::Hash) -> DispatchResult {
+            let caller = ensure_signed(origin)?;
+
+            let mut stored_file = Files::<T>::get(&merkle_root).ok_or(Error::<T>::FileNotFound)?;
+            let who = Self::acting_owner(&stored_file, &caller).ok_or(Error::<T>::NotOwner)?;
+            let index = stored_file.owners
+                .iter()
+                .position(|(owner, _)| owner == &who)
+                .ok_or(Error::<T>::NotOwner)?;
+            let (_, deposit) = stored_file.owners.remove(index);
+            stored_file.managers.retain(|(_, granted_by)| granted_by != &who);
+            T::Currency::unreserve(&who, deposit);
+            FilesByOwner::<T>::remove(&who, &merkle_root);
+
+            if stored_file.owners.is_empty() {
+                Self::reschedule_expiry(&merkle_root, stored_file.expires_at, None)?;
+                Files::<T>::remove(&merkle_root);
+                PendingDeletions::<T>::insert(&merkle_root, DeletionCursor::Chunks(None));
+                Self::deposit_event(Event::FileRemoved { who, merkle_root });
+            } else {
+                Files::<T>::insert(&merkle_root, stored_file);
+                Self::deposit_event(Event::OwnerRemoved { who, merkle_root });
+            }
+
+            Ok(())
+        }
+
+        /// Opens a multi-part upload session, for files too big to fit in a single extrinsic.
+        /// The file is then sent in parts of `MaxPartSize` bytes with `upload_part`, and stored
+        /// with `finalize_upload`, which checks its merkle root is `expected_root`.
+        ///
+        /// A deposit for `total_size` bytes is reserved while the parts are stored. Sessions that
+        /// are not finalized within `UploadTimeout` blocks are aborted, dropping their parts and
+        /// releasing the deposit. The `retention` period of the file starts once it is finalized.
+        #[pallet::weight(T::WeightInfo::begin_upload())]
+        #[pallet::call_index(2)]
+        pub fn begin_upload(
+            origin: OriginFor<T>,
+            total_size: u32,
+            expected_root: T::Hash,
+            chunk_size_exponent: u8,
+            mode: TreeMode,
+            retention: Option<T::BlockNumber>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Self::ensure_valid_layout(total_size as usize, chunk_size_exponent, mode)?;
+            Self::ensure_valid_retention(retention)?;
+
+            let deposit = T::DepositPerByte::get()
+                .saturating_mul(total_size.into())
+                .saturating_add(T::DepositBase::get());
+            T::Currency::reserve(&who, deposit)?;
+
+            let session_id = NextSessionId::<T>::mutate(|next_id| {
+                let session_id = *next_id;
+                *next_id = next_id.wrapping_add(1);
+                session_id
+            });
+            let timeout = frame_system::Pallet::<T>::block_number().saturating_add(T::UploadTimeout::get());
+            let expires_at = Self::schedule_session_expiry(session_id, timeout)?;
+            UploadSessions::<T>::insert(session_id, UploadSession {
+                owner: who.clone(),
+                total_size,
+                expected_root,
+                chunk_size_exponent,
+                mode,
+                deposit,
+                retention,
+                expires_at,
+            });
+
+            Self::deposit_event(Event::UploadStarted { who, session_id, expected_root });
+
+            Ok(())
+        }
+
+        /// Uploads the part at position `index` of a multi-part upload. Every part but the last
+        /// one must be `MaxPartSize` bytes long. Uploading a part again replaces it.
+        #[pallet::weight(T::WeightInfo::upload_part(bytes.len() as u32))]
+        #[pallet::call_index(3)]
+        pub fn upload_part(
+            origin: OriginFor<T>,
+            session_id: SessionId,
+            index: u32,
+            bytes: Vec<u8>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let session = UploadSessions::<T>::get(session_id).ok_or(Error::<T>::SessionNotFound)?;
+            ensure!(session.owner == who, Error::<T>::NotOwner);
+
+            let parts = Self::parts_for(session.total_size);
+            ensure!(index < parts, Error::<T>::InvalidPart);
+            let part_size = T::MaxPartSize::get();
+            let expected_len = if index == parts - 1 {
+                session.total_size - part_size * (parts - 1)
+            } else {
+                part_size
+            };
+            ensure!(bytes.len() == expected_len as usize, Error::<T>::InvalidPart);
+
+            let part = BoundedVec::<u8, T::MaxPartSize>::try_from(bytes).map_err(|_| Error::<T>::InvalidPart)?;
+            UploadParts::<T>::insert(session_id, index, part);
+
+            Ok(())
+        }
+
+        /// Assembles the parts of a multi-part upload and stores the resulting file, exactly as
+        /// `upload_file` would. The session is closed and its deposit released, in exchange for
+        /// the deposit of the stored file.
+        ///
+        /// `total_size` and `pieces` are witnesses of the size and number of pieces of the file,
+        /// which the call is weighed by. They must match the session, or the call fails with
+        /// `WrongWitness`.
+        ///
+        /// Fails with `MissingParts` until every part has been uploaded, and with `RootMismatch`
+        /// if the merkle root of the file is not the expected one. The session is kept in both
+        /// cases, so that wrong parts can be uploaded again.
+        #[pallet::weight(T::WeightInfo::finalize_upload(*total_size, *pieces))]
+        #[pallet::call_index(4)]
+        pub fn finalize_upload(
+            origin: OriginFor<T>,
+            session_id: SessionId,
+            total_size: u32,
+            pieces: u32,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let session = UploadSessions::<T>::get(session_id).ok_or(Error::<T>::SessionNotFound)?;
+            ensure!(session.owner == who, Error::<T>::NotOwner);
+            ensure!(
+                total_size == session.total_size &&
+                    pieces == Self::pieces_for(session.total_size as usize, session.chunk_size_exponent),
+                Error::<T>::WrongWitness
+            );
+
+            let mut file_bytes = Vec::with_capacity(session.total_size as usize);
+            for index in 0..Self::parts_for(session.total_size) {
+                let part = UploadParts::<T>::get(session_id, index).ok_or(Error::<T>::MissingParts)?;
+                file_bytes.extend_from_slice(&part);
+            }
+
+            let file_merkle_tree = Self::build_tree(file_bytes, session.chunk_size_exponent, session.mode)?;
+            let merkle_root = T::Hash::decode(
+                &mut file_merkle_tree.merkle_root()
+            ).or(Err(Error::<T>::Unhasheable))?;
+            ensure!(merkle_root == session.expected_root, Error::<T>::RootMismatch);
+
+            UploadSessions::<T>::remove(session_id);
+            SessionsExpiring::<T>::mutate_exists(session.expires_at, |session_ids| {
+                if let Some(ids) = session_ids {
+                    ids.retain(|id| id != &session_id);
+                    if ids.is_empty() {
+                        *session_ids = None;
+                    }
+                }
+            });
+            let _ = UploadParts::<T>::clear_prefix(session_id, Self::parts_for(session.total_size), None);
+            T::Currency::unreserve(&who, session.deposit);
+
+            Self::store_file(who, merkle_root, file_merkle_tree, T::TreeStorage::get(), session.retention)?;
+
+            Ok(Some(T::WeightInfo::finalize_upload(total_size, pieces)).into())
+        }
+
+        /// Sets the name, MIME type and description of a file, replacing the previous ones.
+        /// Only the owners of the file and their managers are allowed to do it.
+        #[pallet::weight(T::WeightInfo::set_file_metadata())]
+        #[pallet::call_index(5)]
+        pub fn set_file_metadata(
+            origin: OriginFor<T>,
+            merkle_root: T::Hash,
+            name: Vec<u8>,
+            content_type: Vec<u8>,
+            description: Option<Vec<u8>>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut stored_file = Files::<T>::get(&merkle_root).ok_or(Error::<T>::FileNotFound)?;
+            ensure!(Self::acting_owner(&stored_file, &who).is_some(), Error::<T>::NotOwner);
+
+            stored_file.name = name.try_into().map_err(|_| Error::<T>::MetadataTooLong)?;
+            stored_file.content_type = content_type.try_into().map_err(|_| Error::<T>::MetadataTooLong)?;
+            stored_file.description = description
+                .map(|description| description.try_into())
+                .transpose()
+                .map_err(|_| Error::<T>::MetadataTooLong)?;
+            Files::<T>::insert(&merkle_root, stored_file);
+
+            Self::deposit_event(Event::FileMetadataSet { who, merkle_root });
+
+            Ok(())
+        }
+
+        /// Hands the caller's share of a file to another account, which becomes an owner in its
+        /// place. The deposit reserved by the caller is moved to the new owner, still reserved.
+        /// The managers granted by the caller lose their rights.
+        #[pallet::weight(T::WeightInfo::transfer_file())]
+        #[pallet::call_index(6)]
+        pub fn transfer_file(
+            origin: OriginFor<T>,
+            merkle_root: T::Hash,
+            new_owner: AccountIdLookupOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let new_owner = T::Lookup::lookup(new_owner)?;
+
+            let mut stored_file = Files::<T>::get(&merkle_root).ok_or(Error::<T>::FileNotFound)?;
+            ensure!(
+                !stored_file.owners.iter().any(|(owner, _)| owner == &new_owner),
+                Error::<T>::AlreadyOwner
+            );
+            let (owner, deposit) = stored_file.owners
+                .iter_mut()
+                .find(|(owner, _)| owner == &who)
+                .ok_or(Error::<T>::NotOwner)?;
+            let remaining = T::Currency::repatriate_reserved(&who, &new_owner, *deposit, BalanceStatus::Reserved)?;
+            *deposit = deposit.saturating_sub(remaining);
+            *owner = new_owner.clone();
+            stored_file.managers.retain(|(_, granted_by)| granted_by != &who);
+            Files::<T>::insert(&merkle_root, stored_file);
+            FilesByOwner::<T>::remove(&who, &merkle_root);
+            FilesByOwner::<T>::insert(&new_owner, &merkle_root, ());
+
+            Self::deposit_event(Event::OwnershipTransferred { merkle_root, from: who, to: new_owner });
+
+            Ok(())
+        }
+
+        /// Grants an account the right to edit the metadata of a file and to remove it on behalf
+        /// of the caller, which must be one of its owners.
+        #[pallet::weight(T::WeightInfo::add_manager())]
+        #[pallet::call_index(7)]
+        pub fn add_manager(
+            origin: OriginFor<T>,
+            merkle_root: T::Hash,
+            manager: AccountIdLookupOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let manager = T::Lookup::lookup(manager)?;
+
+            let mut stored_file = Files::<T>::get(&merkle_root).ok_or(Error::<T>::FileNotFound)?;
+            ensure!(stored_file.owners.iter().any(|(owner, _)| owner == &who), Error::<T>::NotOwner);
+            ensure!(
+                !stored_file.managers.iter().any(|(account, _)| account == &manager),
+                Error::<T>::AlreadyManager
+            );
+            stored_file.managers
+                .try_push((manager.clone(), who.clone()))
+                .map_err(|_| Error::<T>::TooManyManagers)?;
+            Files::<T>::insert(&merkle_root, stored_file);
+
+            Self::deposit_event(Event::ManagerAdded { merkle_root, owner: who, manager });
+
+            Ok(())
+        }
+
+        /// Revokes the manager rights over a file the caller granted to an account.
+        #[pallet::weight(T::WeightInfo::remove_manager())]
+        #[pallet::call_index(8)]
+        pub fn remove_manager(
+            origin: OriginFor<T>,
+            merkle_root: T::Hash,
+            manager: AccountIdLookupOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let manager = T::Lookup::lookup(manager)?;
+
+            let mut stored_file = Files::<T>::get(&merkle_root).ok_or(Error::<T>::FileNotFound)?;
+            let index = stored_file.managers
+                .iter()
+                .position(|(account, granted_by)| account == &manager && granted_by == &who)
+                .ok_or(Error::<T>::NotManager)?;
+            stored_file.managers.remove(index);
+            Files::<T>::insert(&merkle_root, stored_file);
+
+            Self::deposit_event(Event::ManagerRemoved { merkle_root, owner: who, manager });
+
+            Ok(())
+        }
+
+        /// Stores a file like `upload_file`, but keeps its bytes out of the state: only its merkle
+        /// root and metadata are stored, and the deposit does not depend on its size. The bytes are
+        /// indexed in the block body under the merkle root, so that the nodes keeping indexed
+        /// transactions can serve the file and its proofs.
+        ///
+        /// The indexed bytes are the tail of the extrinsic, so `file_bytes` must be the last
+        /// argument of the call.
+        #[pallet::weight(T::WeightInfo::store_indexed(
+            file_bytes.len() as u32,
+            Pallet::<T>::pieces_for(file_bytes.len(), *chunk_size_exponent),
+        ))]
+        #[pallet::call_index(9)]
+        pub fn store_indexed(
+            origin: OriginFor<T>,
+            chunk_size_exponent: u8,
+            mode: TreeMode,
+            retention: Option<T::BlockNumber>,
+            file_bytes: Vec<u8>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let extrinsic_index = frame_system::Pallet::<T>::extrinsic_index().ok_or(Error::<T>::BadContext)?;
+
+            let size = file_bytes.len() as u32;
+            let file_merkle_tree = Self::build_tree(file_bytes, chunk_size_exponent, mode)?;
+            let merkle_root = T::Hash::decode(
+                &mut file_merkle_tree.merkle_root()
+            ).or(Err(Error::<T>::Unhasheable))?;
+            let content_hash = Hash::decode(
+                &mut file_merkle_tree.merkle_root()
+            ).or(Err(Error::<T>::Unhasheable))?;
+
+            Self::store_file(who, merkle_root, file_merkle_tree, TreeStorage::Indexed, retention)?;
+            sp_io::transaction_index::index(extrinsic_index, size, content_hash);
+
+            Ok(())
+        }
+
+        /// Registers the caller as a storage provider, reserving `ProviderBond` from it.
+        /// Providers declare the files they hold with `declare_file`, and every block some of them
+        /// are challenged to prove it. A provider that misses a challenge loses its bond.
+        #[pallet::weight(T::WeightInfo::register_provider())]
+        #[pallet::call_index(10)]
+        pub fn register_provider(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(!Providers::<T>::contains_key(&who), Error::<T>::AlreadyProvider);
+            // challenges issued before the account was slashed may still be open
+            ensure!(Challenges::<T>::iter_prefix(&who).next().is_none(), Error::<T>::OpenChallenges);
+            let bond = T::ProviderBond::get();
+            T::Currency::reserve(&who, bond)?;
+            ProviderAccounts::<T>::try_append(&who).map_err(|_| Error::<T>::TooManyProviders)?;
+            Providers::<T>::insert(&who, StorageProvider {
+                bond,
+                files: BoundedVec::default(),
+                open_challenges: 0,
+            });
+
+            Self::deposit_event(Event::ProviderRegistered { provider: who });
+
+            Ok(())
+        }
+
+        /// Unregisters the caller as a storage provider, releasing its bond. All of its
+        /// challenges must have been answered.
+        #[pallet::weight(T::WeightInfo::unregister_provider())]
+        #[pallet::call_index(11)]
+        pub fn unregister_provider(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let provider = Providers::<T>::get(&who).ok_or(Error::<T>::NotProvider)?;
+            ensure!(provider.open_challenges == 0, Error::<T>::OpenChallenges);
+            T::Currency::unreserve(&who, provider.bond);
+            ProviderAccounts::<T>::mutate(|accounts| accounts.retain(|account| account != &who));
+            Providers::<T>::remove(&who);
+
+            Self::deposit_event(Event::ProviderUnregistered { provider: who });
+
+            Ok(())
+        }
+
+        /// Declares that the caller, a storage provider, holds a stored file. From then on, it
+        /// can be challenged to prove it.
+        ///
+        /// Only files uploaded with `store_indexed` can be declared: the chunks of the other ones
+        /// are in state, so anyone could answer their challenges with `get_proof`.
+        #[pallet::weight(T::WeightInfo::declare_file())]
+        #[pallet::call_index(12)]
+        pub fn declare_file(origin: OriginFor<T>, merkle_root: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut provider = Providers::<T>::get(&who).ok_or(Error::<T>::NotProvider)?;
+            let stored_file = Files::<T>::get(&merkle_root).ok_or(Error::<T>::FileNotFound)?;
+            ensure!(stored_file.tree_storage == TreeStorage::Indexed, Error::<T>::NotIndexed);
+            ensure!(!provider.files.contains(&merkle_root), Error::<T>::AlreadyDeclared);
+            provider.files.try_push(merkle_root).map_err(|_| Error::<T>::TooManyDeclaredFiles)?;
+            Providers::<T>::insert(&who, provider);
+
+            Self::deposit_event(Event::FileDeclared { provider: who, merkle_root });
+
+            Ok(())
+        }
+
+        /// Declares that the caller, a storage provider, no longer holds a file. The challenges
+        /// already issued about it must still be answered.
+        #[pallet::weight(T::WeightInfo::undeclare_file())]
+        #[pallet::call_index(13)]
+        pub fn undeclare_file(origin: OriginFor<T>, merkle_root: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut provider = Providers::<T>::get(&who).ok_or(Error::<T>::NotProvider)?;
+            let index = provider.files
+                .iter()
+                .position(|file| file == &merkle_root)
+                .ok_or(Error::<T>::NotDeclared)?;
+            provider.files.remove(index);
+            Providers::<T>::insert(&who, provider);
+
+            Self::deposit_event(Event::FileUndeclared { provider: who, merkle_root });
+
+            Ok(())
+        }
+
+        /// Answers a challenge with the content of the challenged chunk and its sibling hashes,
+        /// as returned by `get_indexed_proof`, which must verify against the merkle root of the
+        /// file. Valid answers do not pay fees, while the ones that fail do.
+        #[pallet::weight(T::WeightInfo::respond_challenge(content.len() as u32, siblings.len() as u32))]
+        #[pallet::call_index(14)]
+        pub fn respond_challenge(
+            origin: OriginFor<T>,
+            challenge_id: ChallengeId,
+            content: BoundedVec<u8, MaxChunkSize<T>>,
+            siblings: BoundedVec<Hash, MaxProofDepth<T>>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let challenge = Challenges::<T>::get(&who, challenge_id).ok_or(Error::<T>::ChallengeNotFound)?;
+            let proof = MerkleProof {
+                leaf_index: challenge.chunk_index,
+                pieces: challenge.pieces,
+                chunk_size: challenge.chunk_size,
+                mode: challenge.mode,
+                siblings: siblings.into_inner(),
+                content: content.into_inner(),
+            };
+            ensure!(proof.verify::<T::FileHasher>(challenge.merkle_root.as_ref()), Error::<T>::InvalidProof);
+            Challenges::<T>::remove(&who, challenge_id);
+            ChallengesExpiring::<T>::remove(challenge.deadline, challenge_id);
+            Providers::<T>::mutate(&who, |provider| {
+                if let Some(provider) = provider {
+                    provider.open_challenges = provider.open_challenges.saturating_sub(1);
+                }
+            });
+
+            Self::deposit_event(Event::ChallengeAnswered { challenge_id, provider: who });
+
+            Ok(Pays::No.into())
+        }
+
+        /// Extends the retention period of a file by `extra_blocks` blocks. Only the owners of the
+        /// file and their managers are allowed to do it. Files uploaded without a retention period
+        /// never expire, so they cannot be renewed.
+        #[pallet::weight(T::WeightInfo::renew_file())]
+        #[pallet::call_index(15)]
+        pub fn renew_file(
+            origin: OriginFor<T>,
+            merkle_root: T::Hash,
+            extra_blocks: T::BlockNumber,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut stored_file = Files::<T>::get(&merkle_root).ok_or(Error::<T>::FileNotFound)?;
+            ensure!(Self::acting_owner(&stored_file, &who).is_some(), Error::<T>::NotOwner);
+            let expires_at = stored_file.expires_at.ok_or(Error::<T>::NotExpiring)?;
+            ensure!(!extra_blocks.is_zero(), Error::<T>::InvalidRetention);
+            let renewed = Self::reschedule_expiry(
+                &merkle_root,
+                Some(expires_at),
+                Some(expires_at.saturating_add(extra_blocks)),
+            )?.unwrap_or(expires_at);
+            stored_file.expires_at = Some(renewed);
+            Files::<T>::insert(&merkle_root, stored_file);
+
+            Self::deposit_event(Event::FileRenewed { who, merkle_root, expires_at: renewed });
+
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Checks that a retention period lasts at least one block.
+        fn ensure_valid_retention(retention: Option<T::BlockNumber>) -> DispatchResult {
+            ensure!(retention.map_or(true, |retention| !retention.is_zero()), Error::<T>::InvalidRetention);
+            Ok(())
+        }
+
+        /// Moves the expiry of a file from block `from` to block `to`, `None` meaning that the file
+        /// never expires. If `MaxExpiriesPerBlock` files already expire at `to`, the file expires
+        /// at the first block after it with room left, up to `MaxExpiryDelay` blocks later.
+        /// Returns the block the file now expires at, and fails with `TooManyExpiries` if all of
+        /// them are full.
+        fn reschedule_expiry(
+            merkle_root: &T::Hash,
+            from: Option<T::BlockNumber>,
+            to: Option<T::BlockNumber>,
+        ) -> Result<Option<T::BlockNumber>, DispatchError> {
+            if let Some(from) = from {
+                FilesExpiring::<T>::mutate_exists(from, |merkle_roots| {
+                    if let Some(roots) = merkle_roots {
+                        roots.retain(|root| root != merkle_root);
+                        if roots.is_empty() {
+                            *merkle_roots = None;
+                        }
+                    }
+                });
+            }
+            let to = match to {
+                Some(to) => to,
+                None => return Ok(None),
+            };
+            let mut expires_at = to;
+            for _ in 0..=T::MaxExpiryDelay::get() {
+                if FilesExpiring::<T>::try_append(expires_at, merkle_root).is_ok() {
+                    return Ok(Some(expires_at));
+                }
+                expires_at = expires_at.saturating_add(1u32.into());
+            }
+            Err(Error::<T>::TooManyExpiries.into())
+        }
+
+        /// Schedules the abort of an upload session at block `at`. If `MaxExpiriesPerBlock`
+        /// sessions already expire at `at`, the session expires at the first block after it with
+        /// room left, up to `MaxExpiryDelay` blocks later.
+        fn schedule_session_expiry(session_id: SessionId, at: T::BlockNumber) -> Result<T::BlockNumber, DispatchError> {
+            let mut expires_at = at;
+            for _ in 0..=T::MaxExpiryDelay::get() {
+                if SessionsExpiring::<T>::try_append(expires_at, session_id).is_ok() {
+                    return Ok(expires_at);
+                }
+                expires_at = expires_at.saturating_add(1u32.into());
+            }
+            Err(Error::<T>::TooManyExpiries.into())
+        }
+
+        /// Removes a file whose retention period has ended from all of its owners, releasing their
+        /// deposits, and emits `FileExpired`. Its chunks and merkle tree nodes are deleted
+        /// afterwards, like the ones of removed files.
+        pub(crate) fn expire_file(merkle_root: T::Hash) {
+            let stored_file = match Files::<T>::take(&merkle_root) {
+                Some(stored_file) => stored_file,
+                None => return,
+            };
+            for (owner, deposit) in stored_file.owners {
+                T::Currency::unreserve(&owner, deposit);
+                FilesByOwner::<T>::remove(&owner, &merkle_root);
+            }
+            PendingDeletions::<T>::insert(&merkle_root, DeletionCursor::Chunks(None));
+
+            Self::deposit_event(Event::FileExpired { merkle_root });
+        }
+
+        /// Challenges a random storage provider to prove it holds a random chunk of one of the
+        /// files it declared, within `ChallengePeriod` blocks. `index` tells apart the challenges
+        /// issued in the same block.
+        pub(crate) fn issue_challenge(now: T::BlockNumber, index: u32) {
+            let accounts = ProviderAccounts::<T>::get();
+            if accounts.is_empty() {
+                return;
+            }
+            let (random, _) = T::Randomness::random(&(b"challenge", index).encode());
+            let (provider_index, file_index, chunk_index) =
+                <(u32, u32, u32)>::decode(&mut TrailingZeroInput::new(random.as_ref())).unwrap_or_default();
+            let account = &accounts[provider_index as usize % accounts.len()];
+            let mut provider = match Providers::<T>::get(account) {
+                Some(provider) if !provider.files.is_empty() => provider,
+                _ => return,
+            };
+            let merkle_root = provider.files[file_index as usize % provider.files.len()];
+            // the file may have been removed since the provider declared it, and uploaded again
+            // with its chunks in state
+            let file = match Files::<T>::get(&merkle_root) {
+                Some(file) if file.tree_storage == TreeStorage::Indexed => file,
+                _ => return,
+            };
+
+            let challenge_id = NextChallengeId::<T>::mutate(|next_id| {
+                let challenge_id = *next_id;
+                *next_id = next_id.wrapping_add(1);
+                challenge_id
+            });
+            let chunk_index = chunk_index % file.pieces;
+            let deadline = now.saturating_add(T::ChallengePeriod::get());
+            Challenges::<T>::insert(account, challenge_id, Challenge {
                 merkle_root,
-                pieces: file_merkle_tree.pieces,
+                chunk_index,
+                pieces: file.pieces,
+                chunk_size: file.chunk_size,
+                mode: file.mode,
+                deadline,
             });
+            ChallengesExpiring::<T>::insert(deadline, challenge_id, account);
+            provider.open_challenges = provider.open_challenges.saturating_add(1);
+            Providers::<T>::insert(account, provider);
 
+            Self::deposit_event(Event::ChallengeIssued {
+                challenge_id,
+                provider: account.clone(),
+                merkle_root,
+                chunk_index,
+                deadline,
+            });
+        }
+
+        /// Slashes the whole bond of a storage provider that did not answer a challenge in time,
+        /// and unregisters it. The slashed funds are burnt.
+        pub(crate) fn expire_challenge(account: T::AccountId, challenge_id: ChallengeId) {
+            if Challenges::<T>::take(&account, challenge_id).is_none() {
+                return;
+            }
+            // the provider may have been slashed already, by another challenge
+            if let Some(provider) = Providers::<T>::take(&account) {
+                let (_, not_slashed) = T::Currency::slash_reserved(&account, provider.bond);
+                ProviderAccounts::<T>::mutate(|accounts| accounts.retain(|provider| provider != &account));
+                Self::deposit_event(Event::ProviderSlashed {
+                    challenge_id,
+                    provider: account,
+                    amount: provider.bond.saturating_sub(not_slashed),
+                });
+            }
+        }
+
+        /// Owner on behalf of which `who` acts on a file: itself if it is an owner, or the owner
+        /// that granted it manager rights.
+        fn acting_owner(stored_file: &StoredFile<T>, who: &T::AccountId) -> Option<T::AccountId> {
+            if stored_file.owners.iter().any(|(owner, _)| owner == who) {
+                return Some(who.clone());
+            }
+            stored_file.managers
+                .iter()
+                .find(|(manager, _)| manager == who)
+                .map(|(_, granted_by)| granted_by.clone())
+        }
+
+        /// Number of pieces a file of `size` bytes is divided in, given the chunk size exponent.
+        fn pieces_for(size: usize, chunk_size_exponent: u8) -> u32 {
+            let chunk_size = 1u64.checked_shl(chunk_size_exponent as u32).unwrap_or(u64::MAX);
+            ((size as u64).saturating_add(chunk_size - 1) / chunk_size) as u32
+        }
+
+        /// Checks that a file of `size` bytes can be divided in chunks of `2^chunk_size_exponent`
+        /// bytes with the given tree mode.
+        fn ensure_valid_layout(size: usize, chunk_size_exponent: u8, mode: TreeMode) -> DispatchResult {
+            ensure!(size > 0, Error::<T>::EmptyFile);
+            ensure!(size <= T::MaxFileSize::get() as usize, Error::<T>::FileTooLarge);
+            ensure!(
+                (T::MinChunkSizeExponent::get()..=T::MaxChunkSizeExponent::get()).contains(&chunk_size_exponent),
+                Error::<T>::InvalidChunkSize
+            );
+            // BEP 52 blocks are always 16KiB
+            ensure!(
+                mode != TreeMode::Bep52 || 1 << chunk_size_exponent == BEP52_BLOCK_SIZE,
+                Error::<T>::InvalidChunkSize
+            );
+            ensure!(
+                Self::pieces_for(size, chunk_size_exponent) <= T::MaxPieces::get(),
+                Error::<T>::TooManyPieces
+            );
             Ok(())
         }
+
+        /// Builds the merkle tree of a file, checking it fits the pallet configuration.
+        fn build_tree(file_bytes: Vec<u8>, chunk_size_exponent: u8, mode: TreeMode) -> Result<FileMerkleTreeOf<T>, DispatchError> {
+            Self::ensure_valid_layout(file_bytes.len(), chunk_size_exponent, mode)?;
+            let file_merkle_tree = match mode {
+                TreeMode::Standard => FileMerkleTreeOf::<T>::new(file_bytes, 1 << chunk_size_exponent),
+                TreeMode::Bep52 => FileMerkleTreeOf::<T>::new_bep52(file_bytes),
+            };
+            // trees are only missing for empty files
+            file_merkle_tree.ok_or_else(|| Error::<T>::EmptyFile.into())
+        }
+
+        /// Stores a file with `who` as owner, reserving its deposit, and emits `FileUploaded`.
+        /// Uploading a file that is already stored either fails or adds the uploader to its
+        /// co-owners, depending on `DuplicatePolicy`. New files keep the merkle tree nodes
+        /// selected by `tree_storage`.
+        ///
+        /// Files with a `retention` period expire once it has passed. When a file is shared, the
+        /// latest expiry of its owners is kept, and it never expires if one of them did not set
+        /// a retention period.
+        pub(crate) fn store_file(
+            who: T::AccountId,
+            merkle_root: T::Hash,
+            file_merkle_tree: FileMerkleTreeOf<T>,
+            tree_storage: TreeStorage,
+            retention: Option<T::BlockNumber>,
+        ) -> DispatchResult {
+            ensure!(!FilesMigration::<T>::exists(), Error::<T>::MigrationOngoing);
+            Self::ensure_valid_retention(retention)?;
+            let now = frame_system::Pallet::<T>::block_number();
+            let expires_at = retention.map(|retention| now.saturating_add(retention));
+            let pieces = file_merkle_tree.pieces;
+            let size = file_merkle_tree.file_bytes.len() as u32;
+            let shared = match Files::<T>::get(&merkle_root) {
+                Some(mut stored_file) => {
+                    let deposit = Self::deposit_for(size, pieces, stored_file.tree_storage);
+                    ensure!(T::DuplicatePolicy::get() == DuplicatePolicy::Share, Error::<T>::FileAlreadyExists);
+                    ensure!(
+                        !stored_file.owners.iter().any(|(owner, _)| owner == &who),
+                        Error::<T>::AlreadyOwner
+                    );
+                    stored_file.owners
+                        .try_push((who.clone(), deposit))
+                        .map_err(|_| Error::<T>::TooManyOwners)?;
+                    T::Currency::reserve(&who, deposit)?;
+                    let expires_at = match (stored_file.expires_at, expires_at) {
+                        (Some(current), Some(new)) => Some(current.max(new)),
+                        _ => None,
+                    };
+                    stored_file.expires_at = Self::reschedule_expiry(&merkle_root, stored_file.expires_at, expires_at)?;
+                    Files::<T>::insert(&merkle_root, stored_file);
+                    true
+                },
+                None => {
+                    let deposit = Self::deposit_for(size, pieces, tree_storage);
+                    let owners = BoundedVec::try_from(vec![(who.clone(), deposit)])
+                        .map_err(|_| Error::<T>::TooManyOwners)?;
+                    T::Currency::reserve(&who, deposit)?;
+                    // what is left of a previous upload of the file, removed since then, is cleared
+                    // first, so that no node the new tree storage does not keep remains. It is the
+                    // same file, so it takes at most as many writes as storing it again.
+                    if PendingDeletions::<T>::take(&merkle_root).is_some() {
+                        let _ = Chunks::<T>::clear_prefix(&merkle_root, u32::MAX, None);
+                        let _ = TreeNodes::<T>::clear_prefix(&merkle_root, u32::MAX, None);
+                    }
+                    if tree_storage.stores_chunks() {
+                        for (index, chunk) in file_merkle_tree.chunks().enumerate() {
+                            let chunk = BoundedVec::<u8, MaxChunkSize<T>>::try_from(chunk.to_vec())
+                                .map_err(|_| Error::<T>::InvalidChunkSize)?;
+                            Chunks::<T>::insert(&merkle_root, index as u32, chunk);
+                        }
+                    }
+                    for (index, node) in file_merkle_tree.nodes().enumerate() {
+                        if tree_storage.stores_node(index as u32, pieces) {
+                            TreeNodes::<T>::insert(&merkle_root, index as u32, node);
+                        }
+                    }
+                    let expires_at = Self::reschedule_expiry(&merkle_root, None, expires_at)?;
+                    // Store the claim with the sender.
+                    Files::<T>::insert(&merkle_root, StoredFile {
+                        owners,
+                        managers: BoundedVec::default(),
+                        size,
+                        pieces,
+                        chunk_size: file_merkle_tree.chunk_size,
+                        mode: file_merkle_tree.mode,
+                        tree_storage,
+                        uploaded_at: now,
+                        name: BoundedVec::default(),
+                        content_type: BoundedVec::default(),
+                        description: None,
+                        expires_at,
+                    });
+                    false
+                },
+            };
+
+            FilesByOwner::<T>::insert(&who, &merkle_root, ());
+
+            // Emit an event that the claim was created.
+            Self::deposit_event(Event::FileUploaded { who, merkle_root, size, pieces, shared });
+
+            Ok(())
+        }
+
+        /// Deletes up to `limit` chunks and merkle tree nodes of a removed file, resuming from the
+        /// cursor kept in `PendingDeletions`. The file is taken out of `PendingDeletions` once all
+        /// of them are gone. Returns the number of items deleted.
+        pub(crate) fn delete_file_items(merkle_root: T::Hash, limit: u32) -> u32 {
+            let mut cursor = match PendingDeletions::<T>::get(&merkle_root) {
+                Some(cursor) => cursor,
+                None => return 0,
+            };
+            let mut deleted = 0;
+            while deleted < limit {
+                let results = match &cursor {
+                    DeletionCursor::Chunks(last) =>
+                        Chunks::<T>::clear_prefix(&merkle_root, limit - deleted, last.as_ref().map(|last| last.as_slice())),
+                    DeletionCursor::TreeNodes(last) =>
+                        TreeNodes::<T>::clear_prefix(&merkle_root, limit - deleted, last.as_ref().map(|last| last.as_slice())),
+                };
+                deleted += results.loops;
+                // a cursor too long to be kept makes the deletion start over, which is slower but
+                // still deletes every item
+                let next = results.maybe_cursor.map(|next| BoundedVec::try_from(next).ok());
+                cursor = match (cursor, next) {
+                    (DeletionCursor::Chunks(_), Some(next)) => DeletionCursor::Chunks(next),
+                    (DeletionCursor::Chunks(_), None) => DeletionCursor::TreeNodes(None),
+                    (DeletionCursor::TreeNodes(_), Some(next)) => DeletionCursor::TreeNodes(next),
+                    (DeletionCursor::TreeNodes(_), None) => {
+                        PendingDeletions::<T>::remove(&merkle_root);
+                        return deleted;
+                    },
+                };
+            }
+            PendingDeletions::<T>::insert(&merkle_root, cursor);
+            deleted
+        }
+
+        /// Drops a multi-part upload session along with its parts, releasing its deposit.
+        /// Returns the number of parts removed.
+        pub(crate) fn abort_session(session_id: SessionId) -> u32 {
+            let session = match UploadSessions::<T>::take(session_id) {
+                Some(session) => session,
+                None => return 0,
+            };
+            let parts = UploadParts::<T>::clear_prefix(session_id, Self::parts_for(session.total_size), None).unique;
+            T::Currency::unreserve(&session.owner, session.deposit);
+            Self::deposit_event(Event::UploadAborted { who: session.owner, session_id });
+            parts
+        }
+
+        /// Number of parts a multi-part upload of `total_size` bytes is sent in.
+        pub(crate) fn parts_for(total_size: u32) -> u32 {
+            let part_size = T::MaxPartSize::get();
+            total_size / part_size + (total_size % part_size != 0) as u32
+        }
+
+        /// Amount to reserve for storing a file of `size` bytes and `pieces` pieces, along with
+        /// the merkle tree nodes selected by `tree_storage`.
+        fn deposit_for(size: u32, pieces: u32, tree_storage: TreeStorage) -> BalanceOf<T> {
+            let chunk_bytes = if tree_storage.stores_chunks() { size } else { 0 };
+            let stored_bytes = chunk_bytes.saturating_add(tree_storage.stored_nodes(pieces).saturating_mul(HASH_SIZE as u32));
+            T::DepositPerByte::get()
+                .saturating_mul(stored_bytes.into())
+                .saturating_add(T::DepositBase::get())
+        }
     }
 
     // RPC methods
     impl<T: Config> Pallet<T> {
-        /// Gets from the storage all file hashes ever submitted.
-        pub fn get_files() -> Vec<(Vec<u8>, u32)> {
+        /// Gets from the storage all file hashes ever submitted, along with their number of
+        /// pieces, chunk size and tree mode.
+        pub fn get_files() -> Vec<(Vec<u8>, u32, u32, TreeMode)> {
             let result = Files::<T>::iter()
-                .map(|(_, (_, tree))| (tree.merkle_root().to_vec(), tree.pieces))
-                .collect::<Vec<(Vec<u8>, u32)>>();
+                .map(|(merkle_root, file)| (merkle_root.encode(), file.pieces, file.chunk_size, file.mode))
+                .collect::<Vec<(Vec<u8>, u32, u32, TreeMode)>>();
             result
         }
 
-        /// Given a file's merkle root hash, gets the merkle proof of a given 1KB-chunk, identified
+        /// Gets a page of at most `limit` file hashes, along with their number of pieces, chunk
+        /// size and tree mode, starting after the `start_after` one. The files are not sorted in
+        /// any meaningful order, but the order is stable as long as no file is added or removed.
+        ///
+        /// Returns the cursor to request the next page with, if there are more files. Pages of
+        /// zero files never have a cursor, so `limit` should be at least 1.
+        pub fn get_files_paged(
+            start_after: Option<Hash>,
+            limit: u32,
+        ) -> (Vec<(Vec<u8>, u32, u32, TreeMode)>, Option<Hash>) {
+            let limit = limit.min(crate::MAX_FILES_PER_PAGE) as usize;
+            let mut files = match start_after.and_then(|hash| T::Hash::decode(&mut hash.as_slice()).ok()) {
+                Some(start_after) => Files::<T>::iter_from(Files::<T>::hashed_key_for(start_after)),
+                None => Files::<T>::iter(),
+            };
+            let page = files.by_ref()
+                .take(limit)
+                .map(|(merkle_root, file)| (merkle_root.encode(), file.pieces, file.chunk_size, file.mode))
+                .collect::<Vec<(Vec<u8>, u32, u32, TreeMode)>>();
+            let next_cursor = match files.next() {
+                Some(_) => page.last().and_then(|(merkle_root, ..)| Hash::try_from(merkle_root.as_slice()).ok()),
+                None => None,
+            };
+            (page, next_cursor)
+        }
+
+        /// Gets the hashes of the files owned by an account, along with their number of pieces,
+        /// chunk size and tree mode.
+        pub fn get_files_by_owner(owner: T::AccountId) -> Vec<(Vec<u8>, u32, u32, TreeMode)> {
+            FilesByOwner::<T>::iter_key_prefix(owner)
+                .filter_map(|merkle_root| Files::<T>::get(merkle_root)
+                    .map(|file| (merkle_root.encode(), file.pieces, file.chunk_size, file.mode)))
+                .collect()
+        }
+
+        /// Gets a stored file out of its merkle root, as given to the runtime API.
+        fn stored_file(merkle_root: &[u8]) -> Result<(T::Hash, StoredFile<T>), FileError> {
+            let key = T::Hash::decode_all(&mut &merkle_root[..]).map_err(|_| FileError::InvalidMerkleRoot)?;
+            let file = Files::<T>::get(&key).ok_or(FileError::FileNotFound)?;
+            Ok((key, file))
+        }
+
+        /// Sorts and deduplicates the chunk positions of a multiproof, checking that all of them
+        /// are in a file of `pieces` chunks.
+        fn leaf_indices(mut positions: Vec<u32>, pieces: u32) -> Result<Vec<u32>, FileError> {
+            positions.sort_unstable();
+            positions.dedup();
+            match positions.last() {
+                None => Err(FileError::NoPositions),
+                Some(&position) if position >= pieces => Err(FileError::PositionOutOfRange { position, pieces }),
+                Some(_) => Ok(positions),
+            }
+        }
+
+        /// Gets the name, MIME type, size, upload block, description and expiry block of a file.
+        pub fn get_file_info(merkle_root: Vec<u8>) -> Result<FileInfo<T::BlockNumber>, FileError> {
+            let (_, file) = Self::stored_file(&merkle_root)?;
+            Ok(FileInfo {
+                name: file.name.into_inner(),
+                content_type: file.content_type.into_inner(),
+                size: file.size,
+                uploaded_at: file.uploaded_at,
+                description: file.description.map(|description| description.into_inner()),
+                expires_at: file.expires_at,
+            })
+        }
+
+        /// Given a file's merkle root hash, gets the merkle proof of a given chunk, identified
         /// by its position.
-        /// Returns a tuple where the first element is the chunk content, and the second is
-        /// the merkle proof.
+        /// Returns the chunk content along with the sibling hashes needed to rebuild the root.
         ///
-        /// The idea is that the client can (and should) use the content to compute the sha256 hash,
+        /// The idea is that the client can (and should) use the content to compute its hash,
         /// and with it hash along with the rest of the proofs until the merkle root is finally computed.
         /// This way it gets proven that the content is authentic in a trustless manner.
-        pub fn get_proof(merkle_root: Vec<u8>, position: u32) -> Option<(Vec<u8>, Vec<Vec<u8>>)> {
-            let key = T::Hash::decode(&mut merkle_root.as_slice()).map_err(|_| None::<T>).ok()?;
-            let (_, merkle_tree) = Files::<T>::get(key)?;
-            merkle_tree.merkle_proof(position)
+        pub fn get_proof(merkle_root: Vec<u8>, position: u32) -> Result<MerkleProof, FileError> {
+            let (key, file) = Self::stored_file(&merkle_root)?;
+            if position >= file.pieces {
+                return Err(FileError::PositionOutOfRange { position, pieces: file.pieces });
+            }
+            // the chunks of indexed files are not stored, see `get_indexed_proof`
+            if !file.tree_storage.stores_chunks() {
+                return Err(FileError::Indexed);
+            }
+            let content = Chunks::<T>::get(&key, position).ok_or(FileError::IncompleteFile)?.into_inner();
+            let siblings = match file.tree_storage {
+                TreeStorage::Full => sibling_indexes(file.pieces, position)
+                    .into_iter()
+                    .map(|index| match file.tree_storage.stores_node(index, file.pieces) {
+                        true => TreeNodes::<T>::get(&key, index),
+                        false => Some(CHUNK_FILLER),
+                    })
+                    .collect::<Option<Vec<Hash>>>()
+                    .ok_or(FileError::IncompleteFile)?,
+                TreeStorage::Leaves => {
+                    let leaves = (0..file.pieces)
+                        .map(|index| TreeNodes::<T>::get(&key, index))
+                        .collect::<Option<Vec<Hash>>>()
+                        .ok_or(FileError::IncompleteFile)?;
+                    siblings_from_leaves::<T::FileHasher>(file.mode, leaves, position)
+                },
+                TreeStorage::RootOnly => {
+                    let leaves = (0..file.pieces)
+                        .map(|index| Chunks::<T>::get(&key, index)
+                            .map(|chunk| file.mode.hash_leaf::<T::FileHasher>(&chunk, file.chunk_size)))
+                        .collect::<Option<Vec<Hash>>>()
+                        .ok_or(FileError::IncompleteFile)?;
+                    siblings_from_leaves::<T::FileHasher>(file.mode, leaves, position)
+                },
+                TreeStorage::Indexed => return Err(FileError::Indexed),
+            };
+            Ok(MerkleProof {
+                leaf_index: position,
+                pieces: file.pieces,
+                chunk_size: file.chunk_size,
+                mode: file.mode,
+                siblings,
+                content,
+            })
+        }
+
+        /// Given a file's merkle root hash, gets a single proof of the chunks at the given
+        /// positions, along with their contents. Sibling hashes shared by several chunks are only
+        /// included once, so downloading many chunks takes a single call and a much smaller proof
+        /// than calling `get_proof` for each of them.
+        ///
+        /// Fails if no position is given, or if some of them are out of the file.
+        pub fn get_proofs(merkle_root: Vec<u8>, positions: Vec<u32>) -> Result<MultiProof, FileError> {
+            let (key, file) = Self::stored_file(&merkle_root)?;
+            let leaf_indices = Self::leaf_indices(positions, file.pieces)?;
+            let indexes = multi_proof_indexes(file.pieces, &leaf_indices);
+            let hashes = match file.tree_storage {
+                TreeStorage::Full => indexes
+                    .into_iter()
+                    .map(|index| match file.tree_storage.stores_node(index, file.pieces) {
+                        true => TreeNodes::<T>::get(&key, index),
+                        false => Some(CHUNK_FILLER),
+                    })
+                    .collect::<Option<Vec<Hash>>>()
+                    .ok_or(FileError::IncompleteFile)?,
+                TreeStorage::Leaves => {
+                    let leaves = (0..file.pieces)
+                        .map(|index| TreeNodes::<T>::get(&key, index))
+                        .collect::<Option<Vec<Hash>>>()
+                        .ok_or(FileError::IncompleteFile)?;
+                    nodes_from_leaves::<T::FileHasher>(file.mode, leaves, indexes)
+                },
+                TreeStorage::RootOnly => {
+                    let leaves = (0..file.pieces)
+                        .map(|index| Chunks::<T>::get(&key, index)
+                            .map(|chunk| file.mode.hash_leaf::<T::FileHasher>(&chunk, file.chunk_size)))
+                        .collect::<Option<Vec<Hash>>>()
+                        .ok_or(FileError::IncompleteFile)?;
+                    nodes_from_leaves::<T::FileHasher>(file.mode, leaves, indexes)
+                },
+                // the chunks of indexed files are not stored, see `get_indexed_proofs`
+                TreeStorage::Indexed => return Err(FileError::Indexed),
+            };
+            let contents = leaf_indices.iter()
+                .map(|index| Chunks::<T>::get(&key, index).map(|chunk| chunk.into_inner()))
+                .collect::<Option<Vec<Vec<u8>>>>()
+                .ok_or(FileError::IncompleteFile)?;
+            Ok(MultiProof {
+                leaf_indices,
+                pieces: file.pieces,
+                chunk_size: file.chunk_size,
+                mode: file.mode,
+                hashes,
+                contents,
+            })
+        }
+
+        /// Rebuilds the merkle tree of a file stored with `store_indexed` out of the file bytes
+        /// kept by the node in the indexed block body. Fails if the file is not indexed, or if the
+        /// bytes do not match its merkle root.
+        fn indexed_tree(merkle_root: &[u8], file_bytes: Vec<u8>) -> Result<FileMerkleTreeOf<T>, FileError> {
+            let (_, file) = Self::stored_file(merkle_root)?;
+            if file.tree_storage != TreeStorage::Indexed {
+                return Err(FileError::NotIndexed);
+            }
+            let file_merkle_tree = match file.mode {
+                TreeMode::Standard => FileMerkleTreeOf::<T>::new(file_bytes, file.chunk_size),
+                TreeMode::Bep52 => FileMerkleTreeOf::<T>::new_bep52(file_bytes),
+            }.ok_or(FileError::BytesMismatch)?;
+            if file_merkle_tree.merkle_root() != merkle_root || file_merkle_tree.pieces != file.pieces {
+                return Err(FileError::BytesMismatch);
+            }
+            Ok(file_merkle_tree)
+        }
+
+        /// Gets the merkle proof of a chunk of a file stored with `store_indexed`, out of the file
+        /// bytes kept by the node in the indexed block body. Fails if the file is not indexed, or
+        /// if the bytes do not match its merkle root.
+        pub fn get_indexed_proof(merkle_root: Vec<u8>, file_bytes: Vec<u8>, position: u32) -> Result<MerkleProof, FileError> {
+            let file_merkle_tree = Self::indexed_tree(&merkle_root, file_bytes)?;
+            let pieces = file_merkle_tree.pieces;
+            let content = file_merkle_tree.chunks()
+                .nth(position as usize)
+                .ok_or(FileError::PositionOutOfRange { position, pieces })?
+                .to_vec();
+            let nodes = file_merkle_tree.nodes().collect::<Vec<Hash>>();
+            let siblings = sibling_indexes(pieces, position)
+                .into_iter()
+                .map(|index| nodes[index as usize])
+                .collect();
+            Ok(MerkleProof {
+                leaf_index: position,
+                pieces,
+                chunk_size: file_merkle_tree.chunk_size,
+                mode: file_merkle_tree.mode,
+                siblings,
+                content,
+            })
+        }
+
+        /// Gets a single proof of several chunks of a file stored with `store_indexed`, just like
+        /// `get_proofs`, out of the file bytes kept by the node in the indexed block body.
+        pub fn get_indexed_proofs(merkle_root: Vec<u8>, file_bytes: Vec<u8>, positions: Vec<u32>) -> Result<MultiProof, FileError> {
+            let file_merkle_tree = Self::indexed_tree(&merkle_root, file_bytes)?;
+            let leaf_indices = Self::leaf_indices(positions, file_merkle_tree.pieces)?;
+            file_merkle_tree.multi_proof(&leaf_indices).ok_or(FileError::BytesMismatch)
+        }
+
+        /// Given a file's merkle root hash, reads up to `length` bytes of the file, starting at
+        /// `offset`. The bytes are returned as they are, without any proof, for clients that trust
+        /// the node. Fewer bytes are returned when the end of the file is reached.
+        ///
+        /// Fails if the offset is out of the file, or if its chunks are not stored, as for the files
+        /// stored with `store_indexed`.
+        pub fn get_file_bytes(merkle_root: Vec<u8>, offset: u32, length: u32) -> Result<Vec<u8>, FileError> {
+            let (key, file) = Self::stored_file(&merkle_root)?;
+            if offset >= file.size {
+                return Err(FileError::OffsetOutOfRange { offset, size: file.size });
+            }
+            if !file.tree_storage.stores_chunks() {
+                return Err(FileError::Indexed);
+            }
+            let end = offset.saturating_add(length).min(file.size);
+            let mut bytes = Vec::with_capacity((end - offset) as usize);
+            if end == offset {
+                return Ok(bytes);
+            }
+            for index in offset / file.chunk_size..=(end - 1) / file.chunk_size {
+                let chunk = Chunks::<T>::get(&key, index).ok_or(FileError::IncompleteFile)?;
+                let chunk_start = index * file.chunk_size;
+                let from = offset.saturating_sub(chunk_start) as usize;
+                let to = (end - chunk_start) as usize;
+                bytes.extend_from_slice(chunk.get(from..to.min(chunk.len())).ok_or(FileError::IncompleteFile)?);
+            }
+            Ok(bytes)
+        }
+
+        /// Reads up to `length` bytes of a file stored with `store_indexed`, starting at `offset`,
+        /// out of the file bytes kept by the node in the indexed block body. Like for
+        /// `get_indexed_proof`, fails if the file is not indexed or if the bytes do not match its
+        /// merkle root, so that only the bytes of the file are ever returned.
+        pub fn get_indexed_file_bytes(
+            merkle_root: Vec<u8>,
+            file_bytes: Vec<u8>,
+            offset: u32,
+            length: u32,
+        ) -> Result<Vec<u8>, FileError> {
+            let file_merkle_tree = Self::indexed_tree(&merkle_root, file_bytes)?;
+            let size = file_merkle_tree.file_bytes.len() as u32;
+            if offset >= size {
+                return Err(FileError::OffsetOutOfRange { offset, size });
+            }
+            let end = offset.saturating_add(length).min(size);
+            Ok(file_merkle_tree.file_bytes[offset as usize..end as usize].to_vec())
+        }
+
+        /// Gets the files uploaded in the current block, out of its `FileUploaded` events. Called
+        /// at a given block, it returns the files uploaded in that block.
+        pub fn get_uploaded_files() -> Vec<UploadedFile<T::AccountId>>
+            where <T as Config>::RuntimeEvent: TryInto<Event<T>>,
+        {
+            frame_system::Pallet::<T>::read_events_no_consensus()
+                .into_iter()
+                .filter_map(|record| <T as Config>::RuntimeEvent::from(record.event).try_into().ok())
+                .filter_map(|event| match event {
+                    Event::FileUploaded { who, merkle_root, size, pieces, shared } => Some(UploadedFile {
+                        merkle_root: merkle_root.encode(),
+                        owner: who,
+                        size,
+                        pieces,
+                        shared,
+                    }),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        /// Gets the open challenges of a storage provider: their id, the merkle root of the file
+        /// and the position of the chunk to prove.
+        pub fn get_challenges(provider: T::AccountId) -> Vec<(ChallengeId, Vec<u8>, u32)> {
+            Challenges::<T>::iter_prefix(&provider)
+                .map(|(challenge_id, challenge)| (challenge_id, challenge.merkle_root.encode(), challenge.chunk_index))
+                .collect()
+        }
+
+        /// Verifies that `content` is the chunk at `position` of the file identified by
+        /// `merkle_root`, given the sibling hashes returned by `get_proof` and the layout of the
+        /// file: its number of pieces, chunk size and tree mode.
+        ///
+        /// This is a pure function: the file does not need to be stored on chain, so clients can
+        /// use it to check proofs obtained from any source.
+        pub fn verify_chunk(
+            merkle_root: Vec<u8>,
+            position: u32,
+            content: Vec<u8>,
+            proof: Vec<Hash>,
+            pieces: u32,
+            chunk_size: u32,
+            mode: TreeMode,
+        ) -> bool {
+            MerkleProof { leaf_index: position, pieces, chunk_size, mode, siblings: proof, content }
+                .verify::<T::FileHasher>(&merkle_root)
         }
     }
 }
\ No newline at end of file
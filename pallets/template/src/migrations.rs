@@ -0,0 +1,219 @@
+//! Migrations of the storage of the template pallet.
+
+/// Migration from the original layout, where every file was stored along with its whole merkle
+/// tree in a single `Files` entry, to the one where its chunks and merkle tree nodes are stored
+/// apart, in `Chunks` and `TreeNodes`.
+pub mod v1 {
+    use crate::{
+        BalanceOf, Chunks, Config, FileMerkleTreeOf, Files, FilesByOwner, FilesMigration, MaxChunkSize, Pallet,
+        StoredFile, TreeNodes, TreeMode, TreeStorage, WeightInfo,
+    };
+    use codec::{Decode, Input};
+    use frame_support::{
+        pallet_prelude::*,
+        storage::unhashed,
+        traits::{GetStorageVersion, OnRuntimeUpgrade},
+    };
+    use sp_runtime::traits::Zero;
+    use sp_std::{marker::PhantomData, vec, vec::Vec};
+
+    const LOG_TARGET: &str = "runtime::template";
+
+    /// Size of the chunks of the files stored before this migration, in bytes.
+    const LEGACY_CHUNK_SIZE: u32 = 1024;
+
+    /// A file as stored before this migration: its size and number of pieces as little endian
+    /// `u32`, then its bytes, and then all of its merkle tree nodes. The nodes are not decoded,
+    /// as the tree is built again.
+    pub struct LegacyFile {
+        pub file_bytes: Vec<u8>,
+        pub pieces: u32,
+    }
+
+    impl Decode for LegacyFile {
+        fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+            let size = u32::decode(input)?;
+            let pieces = u32::decode(input)?;
+            let mut file_bytes = vec![0u8; size as usize];
+            input.read(&mut file_bytes)?;
+            Ok(Self { file_bytes, pieces })
+        }
+    }
+
+    /// Progress of the migration, kept in `FilesMigration` between blocks.
+    #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub struct MigrationProgress<Hash> {
+        /// Merkle root of the last file migrated, which the migration resumes after.
+        pub last: Option<Hash>,
+        /// Number of files migrated so far.
+        pub migrated: u32,
+        /// Number of files dropped so far, because their merkle tree cannot be built again.
+        pub dropped: u32,
+    }
+
+    /// Starts moving the chunks and merkle tree nodes of every file to `Chunks` and `TreeNodes`,
+    /// keeping the nodes selected by `Config::TreeStorage`. The uploader of the file becomes its
+    /// only owner, without any deposit as none was reserved, and the file never expires.
+    ///
+    /// Legacy files can be as big as a block, so they are migrated over the next blocks with their
+    /// spare weight, by `migrate_files`. No file can be stored until the migration is done, which
+    /// also sets the storage version to 1. Until a file is migrated, it cannot be read or
+    /// changed.
+    ///
+    /// Legacy trees were built with `sha256`, in `TreeMode::Standard`. Files whose merkle root
+    /// cannot be built again with the configured `FileHasher` are dropped, and counted.
+    pub struct MigrateToV1<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+        fn on_runtime_upgrade() -> Weight {
+            if Pallet::<T>::on_chain_storage_version() != 0 || FilesMigration::<T>::exists() {
+                log::info!(target: LOG_TARGET, "Files already migrated to v1, or being migrated");
+                return T::DbWeight::get().reads(2);
+            }
+
+            FilesMigration::<T>::put(MigrationProgress { last: None, migrated: 0, dropped: 0 });
+            log::info!(target: LOG_TARGET, "Migrating files to v1 over the next blocks");
+            T::DbWeight::get().reads_writes(2, 1)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+            Ok((Files::<T>::iter_keys().count() as u32).encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+            let files = u32::decode(&mut &state[..]).map_err(|_| "Invalid pre-upgrade state")?;
+            let dropped = match FilesMigration::<T>::get() {
+                Some(progress) => progress.dropped,
+                None => {
+                    ensure!(Pallet::<T>::on_chain_storage_version() == 1, "Storage version not updated");
+                    0
+                },
+            };
+            // every file is either still stored, migrated or not, or counted as dropped
+            ensure!(Files::<T>::iter_keys().count() as u32 + dropped == files, "Some files were lost");
+            Ok(())
+        }
+    }
+
+    /// Migrates the legacy files following the last migrated one, as long as they fit in
+    /// `remaining_weight`, and completes the migration once all of them are. Every file is weighed
+    /// like the upload of a file of the same size. Returns the weight used.
+    pub fn migrate_files<T: Config>(remaining_weight: Weight) -> Weight {
+        let mut used_weight = T::DbWeight::get().reads_writes(1, 1);
+        if used_weight.any_gt(remaining_weight) {
+            return Weight::zero();
+        }
+        let mut progress = match FilesMigration::<T>::get() {
+            Some(progress) => progress,
+            None => return T::DbWeight::get().reads(1),
+        };
+
+        let now = frame_system::Pallet::<T>::block_number();
+        let tree_storage = T::TreeStorage::get();
+        // legacy entries cannot be decoded as `StoredFile`, so only the keys are iterated
+        let merkle_roots = match progress.last {
+            Some(last) => Files::<T>::iter_keys_from(Files::<T>::hashed_key_for(last)),
+            None => Files::<T>::iter_keys(),
+        };
+        let mut completed = true;
+        for merkle_root in merkle_roots {
+            used_weight.saturating_accrue(T::DbWeight::get().reads(1));
+            let legacy = unhashed::get_raw(&Files::<T>::hashed_key_for(&merkle_root))
+                .and_then(|raw| <(T::AccountId, LegacyFile)>::decode(&mut &raw[..]).ok());
+            let weight = match &legacy {
+                Some((_, legacy)) => T::WeightInfo::upload_file(legacy.file_bytes.len() as u32, legacy.pieces),
+                None => T::DbWeight::get().writes(1),
+            };
+            if used_weight.saturating_add(weight).any_gt(remaining_weight) {
+                completed = false;
+                break;
+            }
+            used_weight.saturating_accrue(weight);
+
+            match legacy.and_then(|(owner, legacy)| migrate_file::<T>(&merkle_root, owner, legacy, now, tree_storage)) {
+                Some(stored_file) => {
+                    Files::<T>::insert(&merkle_root, stored_file);
+                    progress.migrated += 1;
+                },
+                None => {
+                    Files::<T>::remove(&merkle_root);
+                    progress.dropped += 1;
+                },
+            }
+            progress.last = Some(merkle_root);
+        }
+
+        if completed {
+            FilesMigration::<T>::kill();
+            StorageVersion::new(1).put::<Pallet<T>>();
+            log::info!(
+                target: LOG_TARGET,
+                "Migrated {} files to v1, {} files could not be migrated and were dropped",
+                progress.migrated,
+                progress.dropped,
+            );
+        } else {
+            FilesMigration::<T>::put(progress);
+        }
+        used_weight
+    }
+
+    /// Stores the chunks and merkle tree nodes of a legacy file apart, and returns the file to
+    /// store in `Files`. Returns `None` if the file cannot be migrated.
+    fn migrate_file<T: Config>(
+        merkle_root: &T::Hash,
+        owner: T::AccountId,
+        legacy: LegacyFile,
+        now: T::BlockNumber,
+        tree_storage: TreeStorage,
+    ) -> Option<StoredFile<T>> {
+        let tree = match FileMerkleTreeOf::<T>::new(legacy.file_bytes, LEGACY_CHUNK_SIZE) {
+            Some(tree) if tree.merkle_root() == merkle_root.as_ref() && tree.pieces == legacy.pieces => tree,
+            _ => {
+                log::error!(target: LOG_TARGET, "Dropping file {:?}, whose merkle tree cannot be built again", merkle_root);
+                return None;
+            },
+        };
+        let chunks = tree.chunks()
+            .map(|chunk| BoundedVec::<u8, MaxChunkSize<T>>::try_from(chunk.to_vec()))
+            .collect::<Result<Vec<_>, _>>();
+        let chunks = match chunks {
+            Ok(chunks) => chunks,
+            Err(_) => {
+                log::error!(target: LOG_TARGET, "Dropping file {:?}, whose chunks are too big", merkle_root);
+                return None;
+            },
+        };
+        let owners = BoundedVec::try_from(vec![(owner.clone(), BalanceOf::<T>::zero())]).ok()?;
+
+        if tree_storage.stores_chunks() {
+            for (index, chunk) in chunks.into_iter().enumerate() {
+                Chunks::<T>::insert(merkle_root, index as u32, chunk);
+            }
+        }
+        for (index, node) in tree.nodes().enumerate() {
+            if tree_storage.stores_node(index as u32, tree.pieces) {
+                TreeNodes::<T>::insert(merkle_root, index as u32, node);
+            }
+        }
+        FilesByOwner::<T>::insert(&owner, merkle_root, ());
+
+        Some(StoredFile {
+            owners,
+            managers: BoundedVec::default(),
+            size: tree.file_bytes.len() as u32,
+            pieces: tree.pieces,
+            chunk_size: LEGACY_CHUNK_SIZE,
+            mode: TreeMode::Standard,
+            tree_storage,
+            // the upload block was not recorded
+            uploaded_at: now,
+            name: BoundedVec::default(),
+            content_type: BoundedVec::default(),
+            description: None,
+            expires_at: None,
+        })
+    }
+}
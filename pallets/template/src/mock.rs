@@ -0,0 +1,120 @@
+use crate as pallet_template;
+use frame_support::{
+    parameter_types,
+    traits::{ConstU16, ConstU32, ConstU64, ConstU8, Randomness},
+};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Hash, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        TemplateModule: pallet_template,
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = frame_support::traits::Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ConstU16<42>;
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+    type Balance = u64;
+    type RuntimeEvent = RuntimeEvent;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU64<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+}
+
+/// Randomness derived from the subject and the block number, predictable but good enough to
+/// pick challenges in tests.
+pub struct TestRandomness;
+
+impl Randomness<H256, u64> for TestRandomness {
+    fn random(subject: &[u8]) -> (H256, u64) {
+        let block_number = System::block_number();
+        (BlakeTwo256::hash_of(&(subject, block_number)), block_number)
+    }
+}
+
+parameter_types! {
+    pub static DuplicateUploads: pallet_template::DuplicatePolicy = pallet_template::DuplicatePolicy::Share;
+    pub const FileTreeStorage: pallet_template::TreeStorage = pallet_template::TreeStorage::Full;
+}
+
+impl pallet_template::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type MinChunkSizeExponent = ConstU8<6>;
+    // BEP 52 blocks are 16KiB
+    type MaxChunkSizeExponent = ConstU8<14>;
+    type FileHasher = pallet_template::Sha2_256;
+    type MaxFileSize = ConstU32<{ 64 * 1024 }>;
+    type MaxPieces = ConstU32<512>;
+    type DuplicatePolicy = DuplicateUploads;
+    type TreeStorage = FileTreeStorage;
+    type MaxOwners = ConstU32<4>;
+    type MaxManagers = ConstU32<4>;
+    type MaxPartSize = ConstU32<{ 16 * 1024 }>;
+    type UploadTimeout = ConstU64<10>;
+    type MaxExpiriesPerBlock = ConstU32<4>;
+    type MaxExpiryDelay = ConstU32<4>;
+    type MaxNameLength = ConstU32<64>;
+    type MaxContentTypeLength = ConstU32<64>;
+    type MaxDescriptionLength = ConstU32<256>;
+    type Currency = Balances;
+    type DepositBase = ConstU64<100>;
+    type DepositPerByte = ConstU64<1>;
+    type Randomness = TestRandomness;
+    type ProviderBond = ConstU64<1000>;
+    type MaxProviders = ConstU32<8>;
+    type MaxProviderFiles = ConstU32<8>;
+    type ChallengesPerBlock = ConstU32<1>;
+    type ChallengePeriod = ConstU64<5>;
+    type WeightInfo = ();
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut ext: sp_io::TestExternalities = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap().into();
+    // events are not deposited at genesis
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
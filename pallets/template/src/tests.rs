@@ -0,0 +1,1206 @@
+use crate::{
+    file_merkle_tree::{multi_proof_indexes, sibling_indexes, FileMerkleTree},
+    migrations::v1::{migrate_files, MigrateToV1},
+    mock::*,
+    ChallengeId, Chunks, DuplicatePolicy, Error, Event, FileError, Files, FilesByOwner, FilesMigration, PendingDeletions, Providers, SessionsExpiring,
+    TreeNodes, TreeStorage, UploadParts, UploadSessions, WeightInfo,
+};
+use codec::Encode;
+use frame_support::{
+    assert_err, assert_ok,
+    dispatch::Pays,
+    storage::unhashed,
+    traits::{Currency, Get, GetStorageVersion, Hooks, OnRuntimeUpgrade, ReservableCurrency, StorageVersion},
+    weights::Weight,
+    BoundedVec,
+};
+use pallet_template_merkle_proof::{Blake2_256, FileHasher, MerkleProof, Sha2_256, TreeMode, UploadedFile, BEP52_BLOCK_SIZE};
+use sp_core::{bytes::from_hex, H256};
+
+const CHUNK_SIZE: u32 = 64;
+
+/// Piece counts covering single-piece files, padded trees and balanced ones.
+const PIECES: [u32; 9] = [1, 2, 3, 4, 5, 7, 8, 9, 16];
+
+/// Bytes of a file of `pieces` chunks, the last one being `missing` bytes shorter than the
+/// others. None of the bytes is zero, so that dropping one always changes the chunk hash.
+fn file_bytes(pieces: u32, missing: u32) -> Vec<u8> {
+    (0..pieces * CHUNK_SIZE - missing).map(|i| (i % 251 + 1) as u8).collect()
+}
+
+fn tree(pieces: u32, missing: u32) -> FileMerkleTree<Sha2_256> {
+    FileMerkleTree::new(file_bytes(pieces, missing), CHUNK_SIZE).unwrap()
+}
+
+/// The proof of a chunk, with the sibling hashes picked from the whole tree.
+fn chunk_proof<H: FileHasher>(tree: &FileMerkleTree<H>, piece: u32) -> MerkleProof {
+    let nodes = tree.nodes().collect::<Vec<_>>();
+    MerkleProof {
+        leaf_index: piece,
+        pieces: tree.pieces,
+        chunk_size: tree.chunk_size,
+        mode: tree.mode,
+        siblings: sibling_indexes(tree.pieces, piece).into_iter().map(|index| nodes[index as usize]).collect(),
+        content: tree.chunks().nth(piece as usize).unwrap().to_vec(),
+    }
+}
+
+#[test]
+fn sibling_indexes_follow_the_tree_layout() {
+    assert_eq!(sibling_indexes(1, 0), Vec::<u32>::new());
+    assert_eq!(sibling_indexes(2, 1), vec![0]);
+    // leaves 0..4, then nodes 4 and 5, then the root 6
+    assert_eq!(sibling_indexes(3, 0), vec![1, 5]);
+    assert_eq!(sibling_indexes(3, 2), vec![3, 4]);
+    // leaves 0..8, then nodes 8..12, then nodes 12 and 13, then the root 14
+    assert_eq!(sibling_indexes(5, 4), vec![5, 11, 12]);
+    assert_eq!(sibling_indexes(8, 5), vec![4, 11, 12]);
+}
+
+#[test]
+fn sibling_indexes_have_the_proof_depth() {
+    for pieces in PIECES {
+        for piece in 0..pieces {
+            assert_eq!(sibling_indexes(pieces, piece).len(), MerkleProof::depth(pieces));
+        }
+    }
+}
+
+#[test]
+fn proofs_of_every_chunk_verify() {
+    for pieces in PIECES {
+        for missing in [0, 10] {
+            let tree = tree(pieces, missing);
+            for piece in 0..pieces {
+                assert!(chunk_proof(&tree, piece).verify::<Sha2_256>(tree.merkle_root()), "piece {} of {}", piece, pieces);
+            }
+        }
+    }
+}
+
+#[test]
+fn truncated_last_chunk_verifies_as_is() {
+    for pieces in PIECES {
+        let tree = tree(pieces, 10);
+        let mut proof = chunk_proof(&tree, pieces - 1);
+        assert_eq!(proof.content.len(), CHUNK_SIZE as usize - 10);
+        assert!(proof.verify::<Sha2_256>(tree.merkle_root()));
+
+        proof.content.pop();
+        assert!(!proof.verify::<Sha2_256>(tree.merkle_root()));
+    }
+}
+
+#[test]
+fn tampered_proofs_are_rejected() {
+    for pieces in [2, 3, 5, 8] {
+        let tree = tree(pieces, 10);
+        let root = tree.merkle_root();
+        for piece in 0..pieces {
+            let valid = chunk_proof(&tree, piece);
+
+            let mut proof = valid.clone();
+            proof.content[0] ^= 1;
+            assert!(!proof.verify::<Sha2_256>(root));
+
+            for level in 0..valid.siblings.len() {
+                let mut proof = valid.clone();
+                proof.siblings[level][0] ^= 1;
+                assert!(!proof.verify::<Sha2_256>(root));
+            }
+
+            let mut proof = valid.clone();
+            proof.siblings.pop();
+            assert!(!proof.verify::<Sha2_256>(root));
+
+            let mut proof = valid.clone();
+            proof.siblings.push([0u8; 32]);
+            assert!(!proof.verify::<Sha2_256>(root));
+
+            let mut wrong_root = root.to_vec();
+            wrong_root[0] ^= 1;
+            assert!(!valid.verify::<Sha2_256>(&wrong_root));
+            assert!(!valid.verify::<Blake2_256>(root));
+        }
+    }
+}
+
+#[test]
+fn proofs_with_a_wrong_position_are_rejected() {
+    for pieces in [2, 3, 5, 8] {
+        let tree = tree(pieces, 0);
+        let root = tree.merkle_root();
+        for piece in 0..pieces {
+            for leaf_index in (0..pieces).filter(|leaf_index| *leaf_index != piece) {
+                let mut proof = chunk_proof(&tree, piece);
+                proof.leaf_index = leaf_index;
+                assert!(!proof.verify::<Sha2_256>(root), "piece {} proven at {}", piece, leaf_index);
+            }
+
+            let mut proof = chunk_proof(&tree, piece);
+            proof.leaf_index = pieces;
+            assert!(!proof.verify::<Sha2_256>(root));
+        }
+    }
+}
+
+#[test]
+fn proofs_with_a_wrong_piece_count_are_rejected() {
+    let tree = tree(5, 0);
+    let root = tree.merkle_root();
+    let valid = chunk_proof(&tree, 4);
+
+    // fewer pieces than the proven position
+    let mut proof = valid.clone();
+    proof.pieces = 4;
+    assert!(!proof.verify::<Sha2_256>(root));
+
+    // a deeper tree than the one of the proof
+    let mut proof = valid.clone();
+    proof.pieces = 9;
+    assert!(!proof.verify::<Sha2_256>(root));
+
+    // a shallower tree than the one of the proof
+    let mut proof = chunk_proof(&tree, 1);
+    proof.pieces = 2;
+    assert!(!proof.verify::<Sha2_256>(root));
+}
+
+#[test]
+fn proofs_with_a_wrong_chunk_size_are_rejected() {
+    let tree = tree(5, 10);
+    let root = tree.merkle_root();
+    for chunk_size in [CHUNK_SIZE / 2, CHUNK_SIZE * 2, CHUNK_SIZE + 1] {
+        let mut proof = chunk_proof(&tree, 4);
+        proof.chunk_size = chunk_size;
+        assert!(!proof.verify::<Sha2_256>(root));
+    }
+}
+
+#[test]
+fn multi_proof_indexes_leave_out_known_nodes() {
+    // a single leaf needs the same nodes as its own proof
+    assert_eq!(multi_proof_indexes(1, &[0]), Vec::<u32>::new());
+    assert_eq!(multi_proof_indexes(5, &[4]), sibling_indexes(5, 4));
+    // adjacent leaves are each other's sibling
+    assert_eq!(multi_proof_indexes(8, &[0, 1]), vec![9, 13]);
+    assert_eq!(multi_proof_indexes(3, &[1, 2]), vec![0, 3]);
+    // leaves far apart share nothing below the root
+    assert_eq!(multi_proof_indexes(8, &[0, 7]), vec![1, 6, 9, 10]);
+    // all the leaves only need the padding nodes
+    assert_eq!(multi_proof_indexes(5, &[0, 1, 2, 3, 4]), vec![5, 11]);
+    assert_eq!(multi_proof_indexes(8, &[0, 1, 2, 3, 4, 5, 6, 7]), Vec::<u32>::new());
+}
+
+#[test]
+fn single_leaf_multi_proofs_match_chunk_proofs() {
+    for pieces in PIECES {
+        let tree = tree(pieces, 10);
+        for piece in 0..pieces {
+            let proof = chunk_proof(&tree, piece);
+            let multi_proof = tree.multi_proof(&[piece]).unwrap();
+            assert_eq!(multi_proof.leaf_indices, vec![piece]);
+            assert_eq!(multi_proof.hashes, proof.siblings);
+            assert_eq!(multi_proof.contents, vec![proof.content]);
+            assert!(multi_proof.verify::<Sha2_256>(tree.merkle_root()));
+        }
+    }
+}
+
+#[test]
+fn multi_proofs_verify() {
+    for pieces in PIECES {
+        let tree = tree(pieces, 10);
+        let all = (0..pieces).collect::<Vec<_>>();
+        let adjacent = all.iter().copied().skip(1).take(2).collect::<Vec<_>>();
+        let scattered = all.iter().copied().step_by(3).collect::<Vec<_>>();
+        let ends = vec![0, pieces - 1];
+        for leaf_indices in [all, adjacent, scattered, ends] {
+            if leaf_indices.is_empty() {
+                continue
+            }
+            let multi_proof = tree.multi_proof(&leaf_indices).unwrap();
+            assert!(multi_proof.verify::<Sha2_256>(tree.merkle_root()), "{:?} of {}", leaf_indices, pieces);
+            // every chunk is the one its own proof proves
+            for (position, content) in multi_proof.leaf_indices.iter().zip(multi_proof.contents.iter()) {
+                assert_eq!(content, &chunk_proof(&tree, *position).content);
+            }
+            // the proof only holds sibling hashes of the chunk proofs, and never more of them
+            let siblings = multi_proof.leaf_indices.iter()
+                .flat_map(|position| chunk_proof(&tree, *position).siblings)
+                .collect::<Vec<_>>();
+            assert!(multi_proof.hashes.iter().all(|hash| siblings.contains(hash)));
+            assert!(multi_proof.hashes.len() <= siblings.len());
+        }
+    }
+}
+
+#[test]
+fn multi_proofs_of_all_leaves_only_hold_padding() {
+    let balanced = tree(8, 0);
+    let multi_proof = balanced.multi_proof(&(0..8).collect::<Vec<_>>()).unwrap();
+    assert!(multi_proof.hashes.is_empty());
+    assert!(multi_proof.verify::<Sha2_256>(balanced.merkle_root()));
+
+    let padded = tree(5, 10);
+    let multi_proof = padded.multi_proof(&(0..5).collect::<Vec<_>>()).unwrap();
+    let nodes = padded.nodes().collect::<Vec<_>>();
+    assert_eq!(multi_proof.hashes, vec![nodes[5], nodes[11]]);
+    assert!(multi_proof.verify::<Sha2_256>(padded.merkle_root()));
+}
+
+#[test]
+fn multi_proof_positions_are_sorted_and_deduplicated() {
+    let tree = tree(9, 10);
+    let multi_proof = tree.multi_proof(&[7, 2, 7, 0, 2]).unwrap();
+    assert_eq!(multi_proof.leaf_indices, vec![0, 2, 7]);
+    assert_eq!(multi_proof, tree.multi_proof(&[0, 2, 7]).unwrap());
+    assert!(multi_proof.verify::<Sha2_256>(tree.merkle_root()));
+}
+
+#[test]
+fn multi_proofs_out_of_the_file_are_not_built() {
+    let tree = tree(5, 0);
+    assert_eq!(tree.multi_proof(&[]), None);
+    assert_eq!(tree.multi_proof(&[5]), None);
+    assert_eq!(tree.multi_proof(&[0, 5]), None);
+}
+
+#[test]
+fn tampered_multi_proofs_are_rejected() {
+    for pieces in [3, 5, 8, 9] {
+        let tree = tree(pieces, 10);
+        let root = tree.merkle_root();
+        let valid = tree.multi_proof(&[0, 2, pieces - 1]).unwrap();
+        assert!(valid.verify::<Sha2_256>(root));
+
+        for index in 0..valid.hashes.len() {
+            let mut proof = valid.clone();
+            proof.hashes[index][0] ^= 1;
+            assert!(!proof.verify::<Sha2_256>(root));
+        }
+
+        for index in 0..valid.contents.len() {
+            let mut proof = valid.clone();
+            proof.contents[index][0] ^= 1;
+            assert!(!proof.verify::<Sha2_256>(root));
+        }
+
+        // every hash must be used, and none can be missing
+        let mut proof = valid.clone();
+        proof.hashes.push([0u8; 32]);
+        assert!(!proof.verify::<Sha2_256>(root));
+        let mut proof = valid.clone();
+        proof.hashes.pop();
+        assert!(!proof.verify::<Sha2_256>(root));
+
+        // the positions must be sorted, distinct and match the contents
+        let mut proof = valid.clone();
+        proof.leaf_indices.swap(0, 1);
+        proof.contents.swap(0, 1);
+        assert!(!proof.verify::<Sha2_256>(root));
+        let mut proof = valid.clone();
+        proof.leaf_indices[1] = 0;
+        assert!(!proof.verify::<Sha2_256>(root));
+        let mut proof = valid.clone();
+        proof.contents.pop();
+        assert!(!proof.verify::<Sha2_256>(root));
+        let mut proof = valid.clone();
+        proof.leaf_indices[1] = 1;
+        assert!(!proof.verify::<Sha2_256>(root));
+
+        let mut wrong_root = root.to_vec();
+        wrong_root[0] ^= 1;
+        assert!(!valid.verify::<Sha2_256>(&wrong_root));
+    }
+}
+
+/// `pieces root` of a file of five 16KiB blocks and a last block of 1000 bytes, whose byte `i` is
+/// `i % 256`. It is not taken from a torrent client: it was computed separately from this crate
+/// with Python's `hashlib`, following the BEP 52 rules. Every block is hashed with sha256, the last
+/// one as is, the six leaves are padded with two zero hashes, and every pair of nodes is hashed
+/// with sha256.
+const BEP52_PIECES_ROOT: &str = "e4fb510c7f5956e3fa21054e6bbc57a52e1badffb40fcdbc077e7d74cc2f8c47";
+
+fn bep52_tree() -> FileMerkleTree<Blake2_256> {
+    let file_bytes = (0..5 * BEP52_BLOCK_SIZE + 1000).map(|i| i as u8).collect();
+    // BEP 52 trees ignore the configured hashing algorithm
+    FileMerkleTree::new_bep52(file_bytes).unwrap()
+}
+
+#[test]
+fn bep52_merkle_root_is_the_pieces_root() {
+    let tree = bep52_tree();
+    assert_eq!(tree.pieces, 6);
+    assert_eq!(tree.chunk_size, BEP52_BLOCK_SIZE);
+    assert_eq!(tree.merkle_root(), &from_hex(BEP52_PIECES_ROOT).unwrap()[..]);
+}
+
+#[test]
+fn bep52_single_block_root_is_its_hash() {
+    let file_bytes = vec![7u8; 1000];
+    let tree = FileMerkleTree::<Blake2_256>::new_bep52(file_bytes.clone()).unwrap();
+    assert_eq!(tree.merkle_root(), &Sha2_256::hash(&file_bytes)[..]);
+}
+
+#[test]
+fn bep52_proofs_verify() {
+    let tree = bep52_tree();
+    let root = from_hex(BEP52_PIECES_ROOT).unwrap();
+    for piece in 0..tree.pieces {
+        let proof = chunk_proof(&tree, piece);
+        assert_eq!(proof.mode, TreeMode::Bep52);
+        assert!(proof.verify::<Blake2_256>(&root));
+        assert!(proof.verify::<Sha2_256>(&root));
+
+        let mut proof = proof;
+        proof.mode = TreeMode::Standard;
+        assert!(!proof.verify::<Blake2_256>(&root));
+    }
+    assert_eq!(chunk_proof(&tree, 5).content.len(), 1000);
+}
+
+/// Uploads a file in chunks of `CHUNK_SIZE` bytes on behalf of `who`, and returns its merkle root.
+fn upload(who: u64, file_bytes: Vec<u8>, retention: Option<u64>) -> H256 {
+    let merkle_root = H256::from_slice(FileMerkleTree::<Sha2_256>::new(file_bytes.clone(), CHUNK_SIZE).unwrap().merkle_root());
+    assert_ok!(TemplateModule::upload_file(RuntimeOrigin::signed(who), file_bytes, 6, TreeMode::Standard, retention));
+    merkle_root
+}
+
+/// Stores a file with `store_indexed`, so that storage providers can declare it.
+fn store_indexed(who: u64, file_bytes: Vec<u8>) -> H256 {
+    let merkle_root = H256::from_slice(FileMerkleTree::<Sha2_256>::new(file_bytes.clone(), CHUNK_SIZE).unwrap().merkle_root());
+    System::set_extrinsic_index(0);
+    assert_ok!(TemplateModule::store_indexed(RuntimeOrigin::signed(who), 6, TreeMode::Standard, None, file_bytes));
+    merkle_root
+}
+
+/// Registers `who` as the only storage provider, holding the given file, and challenges it.
+/// Returns the challenge and the challenged chunk.
+fn challenge(who: u64, merkle_root: H256) -> (ChallengeId, u32) {
+    assert_ok!(TemplateModule::register_provider(RuntimeOrigin::signed(who)));
+    assert_ok!(TemplateModule::declare_file(RuntimeOrigin::signed(who), merkle_root));
+    TemplateModule::on_initialize(System::block_number());
+    let challenges = TemplateModule::get_challenges(who);
+    assert_eq!(challenges.len(), 1);
+    let (challenge_id, challenged_root, chunk_index) = challenges[0].clone();
+    assert_eq!(challenged_root, merkle_root.encode());
+    (challenge_id, chunk_index)
+}
+
+#[test]
+fn expiries_are_delayed_past_full_blocks() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        // four files can expire at every block, and be delayed by up to four blocks
+        let merkle_roots = (0..20u8).map(|index| upload(1, vec![index + 1; 100], Some(10))).collect::<Vec<_>>();
+        for (index, merkle_root) in merkle_roots.iter().enumerate() {
+            assert_eq!(Files::<Test>::get(merkle_root).unwrap().expires_at, Some(11 + index as u64 / 4));
+        }
+        assert_err!(
+            TemplateModule::upload_file(RuntimeOrigin::signed(1), vec![21; 100], 6, TreeMode::Standard, Some(10)),
+            Error::<Test>::TooManyExpiries
+        );
+
+        // the blocks up to 15 are full, so the renewal is delayed to the last one allowed
+        assert_ok!(TemplateModule::renew_file(RuntimeOrigin::signed(1), merkle_roots[0], 1));
+        assert_eq!(Files::<Test>::get(merkle_roots[0]).unwrap().expires_at, Some(16));
+    });
+}
+
+/// Stores a file with the layout preceding the v1 migration, owned by `owner`, and returns its
+/// merkle tree. Legacy files were divided in 1KB chunks, hashed with sha256.
+fn legacy_file(owner: u64, file_bytes: Vec<u8>) -> FileMerkleTree<Sha2_256> {
+    let tree = FileMerkleTree::<Sha2_256>::new(file_bytes.clone(), 1024).unwrap();
+    let mut legacy_file = owner.encode();
+    legacy_file.extend_from_slice(&(file_bytes.len() as u32).to_le_bytes());
+    legacy_file.extend_from_slice(&tree.pieces.to_le_bytes());
+    legacy_file.extend_from_slice(&file_bytes);
+    legacy_file.extend_from_slice(&tree.merkle_tree);
+    unhashed::put_raw(&Files::<Test>::hashed_key_for(H256::from_slice(tree.merkle_root())), &legacy_file);
+    tree
+}
+
+#[test]
+fn legacy_files_are_migrated() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        StorageVersion::new(0).put::<TemplateModule>();
+        let tree = legacy_file(1, (0..2500u32).map(|i| (i % 251 + 1) as u8).collect());
+        let merkle_root = H256::from_slice(tree.merkle_root());
+
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        // the files are migrated over the next blocks, and no file can be stored meanwhile
+        assert_eq!(TemplateModule::on_chain_storage_version(), 0);
+        assert!(FilesMigration::<Test>::exists());
+        assert_err!(
+            TemplateModule::upload_file(RuntimeOrigin::signed(1), file_bytes(5, 10), 6, TreeMode::Standard, None),
+            Error::<Test>::MigrationOngoing
+        );
+        TemplateModule::on_idle(1, Weight::MAX);
+
+        assert_eq!(TemplateModule::on_chain_storage_version(), 1);
+        assert!(!FilesMigration::<Test>::exists());
+        let stored_file = Files::<Test>::get(merkle_root).unwrap();
+        assert_eq!(stored_file.owners.into_inner(), vec![(1, 0)]);
+        assert_eq!((stored_file.size, stored_file.pieces, stored_file.chunk_size), (2500, 3, 1024));
+        assert!(FilesByOwner::<Test>::contains_key(1, merkle_root));
+        for position in 0..3 {
+            let proof = TemplateModule::get_proof(merkle_root.encode(), position).unwrap();
+            assert_eq!(proof.siblings, chunk_proof(&tree, position).siblings);
+            assert!(verify_chunk(merkle_root, proof));
+        }
+        upload(1, file_bytes(5, 10), None);
+
+        // migrated files are left untouched by further upgrades
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        assert!(!FilesMigration::<Test>::exists());
+        assert_eq!(Files::<Test>::get(merkle_root).map(|file| file.pieces), Some(3));
+    });
+}
+
+#[test]
+fn legacy_files_are_migrated_over_several_blocks() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<TemplateModule>();
+        let merkle_roots = (1..=3u8)
+            .map(|byte| H256::from_slice(legacy_file(1, vec![byte; 2500]).merkle_root()))
+            .collect::<Vec<_>>();
+        // a file whose bytes do not match its merkle root any more
+        let corrupted = H256::repeat_byte(1);
+        let mut legacy_file = 1u64.encode();
+        legacy_file.extend_from_slice(&4u32.to_le_bytes());
+        legacy_file.extend_from_slice(&1u32.to_le_bytes());
+        legacy_file.extend_from_slice(&[1, 2, 3, 4]);
+        unhashed::put_raw(&Files::<Test>::hashed_key_for(corrupted), &legacy_file);
+        MigrateToV1::<Test>::on_runtime_upgrade();
+
+        // enough weight for a single file per block
+        type Weights = <Test as crate::Config>::WeightInfo;
+        let db_weight = <Test as frame_system::Config>::DbWeight::get();
+        let block_weight = db_weight.reads_writes(2, 1).saturating_add(Weights::upload_file(2500, 3));
+        for block in 1..=3 {
+            migrate_files::<Test>(block_weight);
+            let progress = FilesMigration::<Test>::get().unwrap();
+            assert_eq!(progress.migrated + progress.dropped, block);
+        }
+        assert_eq!(TemplateModule::on_chain_storage_version(), 0);
+        migrate_files::<Test>(block_weight);
+
+        assert!(!FilesMigration::<Test>::exists());
+        assert_eq!(TemplateModule::on_chain_storage_version(), 1);
+        assert!(Files::<Test>::get(corrupted).is_none());
+        assert!(!unhashed::exists(&Files::<Test>::hashed_key_for(corrupted)));
+        for merkle_root in merkle_roots {
+            assert_eq!(Files::<Test>::get(merkle_root).map(|file| file.size), Some(2500));
+        }
+    });
+}
+
+/// Calls `verify_chunk` with the fields of a proof.
+fn verify_chunk(merkle_root: H256, proof: MerkleProof) -> bool {
+    TemplateModule::verify_chunk(
+        merkle_root.encode(),
+        proof.leaf_index,
+        proof.content,
+        proof.siblings,
+        proof.pieces,
+        proof.chunk_size,
+        proof.mode,
+    )
+}
+
+#[test]
+fn verify_chunk_checks_proofs_of_any_file() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let merkle_root = upload(1, file_bytes(5, 10), None);
+        for position in 0..5 {
+            let proof = TemplateModule::get_proof(merkle_root.encode(), position).unwrap();
+            assert!(verify_chunk(merkle_root, proof.clone()));
+            assert!(!verify_chunk(merkle_root, MerkleProof { leaf_index: (position + 1) % 5, ..proof.clone() }));
+            assert!(!verify_chunk(merkle_root, MerkleProof { pieces: 16, ..proof.clone() }));
+            assert!(!verify_chunk(merkle_root, MerkleProof { chunk_size: CHUNK_SIZE * 2, ..proof }));
+        }
+
+        // the file does not need to be stored
+        for pieces in PIECES {
+            let tree = tree(pieces, 20);
+            let merkle_root = H256::from_slice(tree.merkle_root());
+            assert!(Files::<Test>::get(merkle_root).is_none());
+            for piece in 0..pieces {
+                assert!(verify_chunk(merkle_root, chunk_proof(&tree, piece)));
+            }
+        }
+    });
+}
+
+#[test]
+fn single_piece_proofs_have_no_siblings() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let content = file_bytes(1, 10);
+        let merkle_root = upload(1, content.clone(), None);
+        let proof = TemplateModule::get_proof(merkle_root.encode(), 0).unwrap();
+        assert_eq!((proof.siblings.len(), &proof.content), (0, &content));
+        assert!(verify_chunk(merkle_root, proof.clone()));
+        assert!(!verify_chunk(merkle_root, MerkleProof { siblings: vec![merkle_root.0], ..proof }));
+    });
+}
+
+#[test]
+fn empty_files_are_rejected() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        assert_err!(
+            TemplateModule::upload_file(RuntimeOrigin::signed(1), Vec::new(), 6, TreeMode::Standard, None),
+            Error::<Test>::EmptyFile
+        );
+        assert_err!(
+            TemplateModule::upload_file(RuntimeOrigin::signed(1), Vec::new(), 14, TreeMode::Bep52, None),
+            Error::<Test>::EmptyFile
+        );
+    });
+}
+
+#[test]
+fn files_with_too_many_pieces_are_rejected() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        // 513 chunks of 64 bytes, one more than `MaxPieces`
+        let file_bytes = vec![1u8; 512 * 64 + 1];
+        assert_err!(
+            TemplateModule::upload_file(RuntimeOrigin::signed(1), file_bytes.clone(), 6, TreeMode::Standard, None),
+            Error::<Test>::TooManyPieces
+        );
+        assert_ok!(TemplateModule::upload_file(RuntimeOrigin::signed(1), file_bytes, 7, TreeMode::Standard, None));
+    });
+}
+
+#[test]
+fn tree_storage_trades_state_for_proof_cost() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        type Weights = <Test as crate::Config>::WeightInfo;
+        // proofs of the full tree always read as many nodes as the deepest one has siblings, so
+        // they only get cheaper than rebuilding the tree past a few pieces
+        for pieces in [16, 100, 512] {
+            let mut stored = Vec::new();
+            let modes = [TreeStorage::Full, TreeStorage::Leaves, TreeStorage::RootOnly];
+            for (missing, tree_storage) in modes.into_iter().enumerate() {
+                // a different file for every mode, so that none of them is shared
+                let tree = tree(pieces, missing as u32 + 1);
+                let merkle_root = H256::from_slice(tree.merkle_root());
+                let expected = chunk_proof(&tree, pieces - 1);
+                assert_ok!(TemplateModule::store_file(1, merkle_root, tree, tree_storage, None));
+                let nodes: usize = TreeNodes::<Test>::iter_prefix_values(merkle_root).map(|node| node.encoded_size()).sum();
+                let chunks: usize = Chunks::<Test>::iter_prefix_values(merkle_root).map(|chunk| chunk.encoded_size()).sum();
+                assert_eq!(TreeNodes::<Test>::iter_prefix(merkle_root).count() as u32, tree_storage.stored_nodes(pieces));
+                assert_eq!(TemplateModule::get_proof(merkle_root.encode(), pieces - 1), Ok(expected));
+                stored.push(nodes + chunks);
+            }
+            // every mode stores less state than the previous one...
+            assert!(stored[0] > stored[1] && stored[1] > stored[2]);
+            // ...and serves proofs at a higher cost
+            let costs = [
+                Weights::get_proof_full(pieces),
+                Weights::get_proof_leaves(pieces),
+                Weights::get_proof_root_only(pieces),
+            ];
+            assert!(costs[0].ref_time() < costs[1].ref_time() && costs[1].ref_time() < costs[2].ref_time());
+        }
+    });
+}
+
+#[test]
+fn only_indexed_files_can_be_declared() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        Balances::make_free_balance_be(&2, 1_000_000);
+        let stored = upload(1, file_bytes(5, 10), None);
+        let indexed = store_indexed(1, file_bytes(5, 20));
+        assert_ok!(TemplateModule::register_provider(RuntimeOrigin::signed(2)));
+        // anyone can read the chunks of the stored file from the state
+        assert_err!(TemplateModule::declare_file(RuntimeOrigin::signed(2), stored), Error::<Test>::NotIndexed);
+        assert_ok!(TemplateModule::declare_file(RuntimeOrigin::signed(2), indexed));
+        assert_err!(TemplateModule::declare_file(RuntimeOrigin::signed(2), indexed), Error::<Test>::AlreadyDeclared);
+    });
+}
+
+#[test]
+fn challenges_are_answered_with_the_proof_of_the_chunk() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        Balances::make_free_balance_be(&2, 1_000_000);
+        let tree = tree(5, 10);
+        let merkle_root = store_indexed(1, file_bytes(5, 10));
+        let (challenge_id, chunk_index) = challenge(2, merkle_root);
+        assert_eq!(Providers::<Test>::get(2).unwrap().open_challenges, 1);
+
+        let proof = chunk_proof(&tree, chunk_index);
+        let siblings = BoundedVec::try_from(proof.siblings).unwrap();
+        let other_chunk = tree.chunks().nth(((chunk_index + 1) % 5) as usize).unwrap().to_vec();
+        assert_err!(
+            TemplateModule::respond_challenge(
+                RuntimeOrigin::signed(2),
+                challenge_id,
+                BoundedVec::try_from(other_chunk).unwrap(),
+                siblings.clone(),
+            ),
+            Error::<Test>::InvalidProof
+        );
+        assert_err!(
+            TemplateModule::respond_challenge(
+                RuntimeOrigin::signed(1),
+                challenge_id,
+                BoundedVec::try_from(proof.content.clone()).unwrap(),
+                siblings.clone(),
+            ),
+            Error::<Test>::ChallengeNotFound
+        );
+        let post_info = TemplateModule::respond_challenge(
+            RuntimeOrigin::signed(2),
+            challenge_id,
+            BoundedVec::try_from(proof.content).unwrap(),
+            siblings,
+        ).unwrap();
+        assert_eq!(post_info.pays_fee, Pays::No);
+        assert!(TemplateModule::get_challenges(2).is_empty());
+        assert_eq!(Providers::<Test>::get(2).unwrap().open_challenges, 0);
+
+        // the answered challenge does not slash the provider once its deadline is reached
+        System::set_block_number(6);
+        TemplateModule::on_initialize(6);
+        assert!(Providers::<Test>::get(2).is_some());
+        assert_eq!(Balances::reserved_balance(&2), 1000);
+    });
+}
+
+#[test]
+fn missed_challenges_slash_the_provider() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        Balances::make_free_balance_be(&2, 1_000_000);
+        let merkle_root = store_indexed(1, file_bytes(5, 10));
+        challenge(2, merkle_root);
+        assert_err!(TemplateModule::unregister_provider(RuntimeOrigin::signed(2)), Error::<Test>::OpenChallenges);
+
+        // the challenge was issued at block 1, with a period of 5 blocks
+        System::set_block_number(6);
+        TemplateModule::on_initialize(6);
+        assert!(Providers::<Test>::get(2).is_none());
+        assert!(TemplateModule::get_challenges(2).is_empty());
+        assert_eq!((Balances::free_balance(&2), Balances::reserved_balance(&2)), (1_000_000 - 1000, 0));
+    });
+}
+
+#[test]
+fn removed_files_are_deleted_in_steps() {
+    let mut ext = new_test_ext();
+    let merkle_root = ext.execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        upload(1, file_bytes(5, 10), None)
+    });
+    // the items must be in the backend, like the ones of the previous blocks, for the deletions
+    // to be limited
+    ext.commit_all().unwrap();
+    ext.execute_with(|| {
+        assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(1), merkle_root));
+        assert!(Files::<Test>::get(merkle_root).is_none());
+        // 5 chunks and 12 merkle tree nodes, deleted 4 at a time from where the last call stopped
+        for _ in 0..4 {
+            assert_eq!(TemplateModule::delete_file_items(merkle_root, 4), 4);
+            assert!(PendingDeletions::<Test>::contains_key(merkle_root));
+        }
+        assert_eq!(TemplateModule::delete_file_items(merkle_root, 4), 1);
+        assert!(!PendingDeletions::<Test>::contains_key(merkle_root));
+        assert_eq!(Chunks::<Test>::iter_prefix(merkle_root).count(), 0);
+        assert_eq!(TreeNodes::<Test>::iter_prefix(merkle_root).count(), 0);
+        assert_eq!(TemplateModule::delete_file_items(merkle_root, 4), 0);
+    });
+}
+
+#[test]
+fn files_uploaded_again_keep_no_stale_items() {
+    let mut ext = new_test_ext();
+    let merkle_root = ext.execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let merkle_root = upload(1, file_bytes(5, 10), None);
+        assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(1), merkle_root));
+        merkle_root
+    });
+    ext.commit_all().unwrap();
+    ext.execute_with(|| {
+        // the file is uploaded again, keeping only the leaves, before all the nodes of the
+        // previous upload are deleted
+        assert_eq!(TemplateModule::delete_file_items(merkle_root, 7), 7);
+        assert_ok!(TemplateModule::store_file(1, merkle_root, tree(5, 10), TreeStorage::Leaves, None));
+        assert!(!PendingDeletions::<Test>::contains_key(merkle_root));
+        assert_eq!(Chunks::<Test>::iter_prefix(merkle_root).count(), 5);
+        assert_eq!(TreeNodes::<Test>::iter_prefix(merkle_root).count() as u32, TreeStorage::Leaves.stored_nodes(5));
+        assert_eq!(TemplateModule::get_proof(merkle_root.encode(), 4), Ok(chunk_proof(&tree(5, 10), 4)));
+
+        // and every item is dropped when it is uploaded again as an indexed file
+        assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(1), merkle_root));
+        store_indexed(1, file_bytes(5, 10));
+        assert!(!PendingDeletions::<Test>::contains_key(merkle_root));
+        assert_eq!(Chunks::<Test>::iter_prefix(merkle_root).count(), 0);
+        assert_eq!(TreeNodes::<Test>::iter_prefix(merkle_root).count(), 0);
+    });
+}
+
+/// Bytes of a file too big for a single part, which takes three of them.
+fn multi_part_bytes() -> Vec<u8> {
+    (0..40_000u32).map(|i| (i % 251 + 1) as u8).collect()
+}
+
+#[test]
+fn multi_part_uploads_store_the_assembled_file() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let file_bytes = multi_part_bytes();
+        let parts = file_bytes.chunks(16 * 1024).map(|part| part.to_vec()).collect::<Vec<_>>();
+        let merkle_root = H256::from_slice(FileMerkleTree::<Sha2_256>::new(file_bytes.clone(), 256).unwrap().merkle_root());
+        assert_ok!(TemplateModule::begin_upload(RuntimeOrigin::signed(1), 40_000, merkle_root, 8, TreeMode::Standard, None));
+        System::assert_last_event(Event::<Test>::UploadStarted { who: 1, session_id: 0, expected_root: merkle_root }.into());
+        assert_eq!(Balances::reserved_balance(&1), 100 + 40_000);
+
+        assert_err!(
+            TemplateModule::upload_part(RuntimeOrigin::signed(2), 0, 0, parts[0].clone()),
+            Error::<Test>::NotOwner
+        );
+        assert_err!(
+            TemplateModule::upload_part(RuntimeOrigin::signed(1), 0, 3, parts[2].clone()),
+            Error::<Test>::InvalidPart
+        );
+        // only the last part can be shorter
+        assert_err!(
+            TemplateModule::upload_part(RuntimeOrigin::signed(1), 0, 1, parts[2].clone()),
+            Error::<Test>::InvalidPart
+        );
+        assert_ok!(TemplateModule::upload_part(RuntimeOrigin::signed(1), 0, 0, parts[0].clone()));
+        assert_ok!(TemplateModule::upload_part(RuntimeOrigin::signed(1), 0, 2, parts[2].clone()));
+        assert_err!(TemplateModule::finalize_upload(RuntimeOrigin::signed(1), 0, 40_000, 157), Error::<Test>::MissingParts);
+
+        // a wrong part is replaced by sending it again
+        assert_ok!(TemplateModule::upload_part(RuntimeOrigin::signed(1), 0, 1, parts[0].clone()));
+        assert_err!(TemplateModule::finalize_upload(RuntimeOrigin::signed(1), 0, 40_000, 157), Error::<Test>::RootMismatch);
+        assert_ok!(TemplateModule::upload_part(RuntimeOrigin::signed(1), 0, 1, parts[1].clone()));
+        assert_err!(TemplateModule::finalize_upload(RuntimeOrigin::signed(1), 0, 40_000, 156), Error::<Test>::WrongWitness);
+        assert_ok!(TemplateModule::finalize_upload(RuntimeOrigin::signed(1), 0, 40_000, 157));
+
+        let stored_file = Files::<Test>::get(merkle_root).unwrap();
+        assert_eq!((stored_file.size, stored_file.pieces, stored_file.chunk_size), (40_000, 157, 256));
+        assert_eq!(TemplateModule::get_proof(merkle_root.encode(), 100).unwrap().content, file_bytes[25_600..25_856].to_vec());
+        // the deposit of the session is swapped for the one of the file
+        assert_eq!(Balances::reserved_balance(&1), stored_file.owners[0].1);
+        assert!(UploadSessions::<Test>::get(0).is_none());
+        assert_eq!(UploadParts::<Test>::iter_prefix(0).count(), 0);
+        assert!(SessionsExpiring::<Test>::get(11).is_empty());
+        assert_err!(TemplateModule::finalize_upload(RuntimeOrigin::signed(1), 0, 40_000, 157), Error::<Test>::SessionNotFound);
+    });
+}
+
+#[test]
+fn unfinished_sessions_are_aborted_when_they_expire() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let merkle_root = H256::repeat_byte(1);
+        assert_ok!(TemplateModule::begin_upload(RuntimeOrigin::signed(1), 40_000, merkle_root, 8, TreeMode::Standard, None));
+        assert_ok!(TemplateModule::upload_part(RuntimeOrigin::signed(1), 0, 0, multi_part_bytes()[..16 * 1024].to_vec()));
+
+        // the session was opened at block 1, with a timeout of 10 blocks
+        System::set_block_number(10);
+        TemplateModule::on_initialize(10);
+        assert!(UploadSessions::<Test>::get(0).is_some());
+        System::set_block_number(11);
+        TemplateModule::on_initialize(11);
+        System::assert_has_event(Event::<Test>::UploadAborted { who: 1, session_id: 0 }.into());
+        assert!(UploadSessions::<Test>::get(0).is_none());
+        assert_eq!(UploadParts::<Test>::iter_prefix(0).count(), 0);
+        assert_eq!(Balances::reserved_balance(&1), 0);
+    });
+}
+
+#[test]
+fn session_expiries_are_delayed_past_full_blocks() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 10_000_000);
+        let begin = || TemplateModule::begin_upload(RuntimeOrigin::signed(1), 40_000, H256::repeat_byte(1), 8, TreeMode::Standard, None);
+        // four sessions can expire at every block, and be delayed by up to four blocks
+        for session_id in 0..20 {
+            assert_ok!(begin());
+            assert_eq!(UploadSessions::<Test>::get(session_id).unwrap().expires_at, 11 + session_id / 4);
+        }
+        assert_err!(begin(), Error::<Test>::TooManyExpiries);
+
+        System::set_block_number(11);
+        TemplateModule::on_initialize(11);
+        assert_eq!(UploadSessions::<Test>::iter().count(), 16);
+        assert!(SessionsExpiring::<Test>::get(11).is_empty());
+    });
+}
+
+#[test]
+fn file_bytes_are_read_in_ranges() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let file_bytes = file_bytes(5, 10);
+        let size = file_bytes.len() as u32;
+        let merkle_root = upload(1, file_bytes.clone(), None).encode();
+        // ranges within a chunk, across chunks and past the end of the file
+        for (offset, length) in [(0, 8), (70, 10), (60, 140), (250, 100), (0, u32::MAX)] {
+            let end = offset.saturating_add(length).min(size) as usize;
+            assert_eq!(TemplateModule::get_file_bytes(merkle_root.clone(), offset, length), Ok(file_bytes[offset as usize..end].to_vec()));
+        }
+        assert_eq!(TemplateModule::get_file_bytes(merkle_root.clone(), 3, 0), Ok(Vec::new()));
+        assert_eq!(TemplateModule::get_file_bytes(merkle_root, size, 1), Err(FileError::OffsetOutOfRange { offset: size, size }));
+    });
+}
+
+#[test]
+fn indexed_file_bytes_are_checked_against_the_merkle_root() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let file_bytes = file_bytes(5, 10);
+        let size = file_bytes.len() as u32;
+        let merkle_root = store_indexed(1, file_bytes.clone()).encode();
+        assert_eq!(TemplateModule::get_file_bytes(merkle_root.clone(), 0, 8), Err(FileError::Indexed));
+        assert_eq!(
+            TemplateModule::get_indexed_file_bytes(merkle_root.clone(), file_bytes.clone(), 60, 140),
+            Ok(file_bytes[60..200].to_vec()),
+        );
+        assert_eq!(
+            TemplateModule::get_indexed_file_bytes(merkle_root.clone(), file_bytes.clone(), size, 1),
+            Err(FileError::OffsetOutOfRange { offset: size, size }),
+        );
+        // the bytes of another file are never returned, even for a range they share with it
+        let mut other_bytes = file_bytes.clone();
+        *other_bytes.last_mut().unwrap() ^= 1;
+        assert_eq!(TemplateModule::get_indexed_file_bytes(merkle_root, other_bytes.clone(), 0, 8), Err(FileError::BytesMismatch));
+        let stored = upload(1, other_bytes.clone(), None).encode();
+        assert_eq!(TemplateModule::get_indexed_file_bytes(stored, other_bytes, 0, 8), Err(FileError::NotIndexed));
+    });
+}
+
+#[test]
+fn deposits_are_reserved_until_the_file_is_removed() {
+    new_test_ext().execute_with(|| {
+        let bytes = file_bytes(5, 10);
+        // the deposit covers the bytes of the chunks and of the tree nodes kept in the state
+        let deposit = 100 + bytes.len() as u64 + TreeStorage::Full.stored_nodes(5) as u64 * 32;
+        Balances::make_free_balance_be(&1, deposit - 1);
+        assert_err!(
+            TemplateModule::upload_file(RuntimeOrigin::signed(1), bytes.clone(), 6, TreeMode::Standard, None),
+            pallet_balances::Error::<Test>::InsufficientBalance
+        );
+
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let merkle_root = upload(1, bytes, None);
+        assert_eq!(Balances::reserved_balance(1), deposit);
+        // the bytes of indexed files are not kept in the state, so they do not pay for them
+        let indexed_deposit = 100 + TreeStorage::Indexed.stored_nodes(5) as u64 * 32;
+        store_indexed(1, file_bytes(5, 20));
+        assert_eq!(Balances::reserved_balance(1), deposit + indexed_deposit);
+
+        assert_err!(TemplateModule::remove_file(RuntimeOrigin::signed(2), merkle_root), Error::<Test>::NotOwner);
+        assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(1), merkle_root));
+        assert_eq!(Balances::reserved_balance(1), indexed_deposit);
+        assert_eq!(Balances::free_balance(1), 1_000_000 - indexed_deposit);
+        System::assert_last_event(Event::FileRemoved { who: 1, merkle_root }.into());
+    });
+}
+
+#[test]
+fn duplicate_uploads_follow_the_duplicate_policy() {
+    new_test_ext().execute_with(|| {
+        for who in 1..=5 {
+            Balances::make_free_balance_be(&who, 1_000_000);
+        }
+        let bytes = file_bytes(5, 10);
+        let upload_again = |who: u64| TemplateModule::upload_file(RuntimeOrigin::signed(who), bytes.clone(), 6, TreeMode::Standard, None);
+        let merkle_root = upload(1, bytes.clone(), None);
+        let deposit = Balances::reserved_balance(1);
+        // the file is stored once, and every co-owner reserves the same deposit
+        for who in 2..=4 {
+            assert_ok!(upload_again(who));
+            System::assert_last_event(Event::FileUploaded { who, merkle_root, size: bytes.len() as u32, pieces: 5, shared: true }.into());
+            assert_eq!(Balances::reserved_balance(who), deposit);
+        }
+        assert_eq!(Files::<Test>::get(merkle_root).unwrap().owners.len(), 4);
+        assert_err!(upload_again(1), Error::<Test>::AlreadyOwner);
+        assert_err!(upload_again(5), Error::<Test>::TooManyOwners);
+
+        // the file is kept until its last owner removes it
+        for who in 1..=3 {
+            assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(who), merkle_root));
+            System::assert_last_event(Event::OwnerRemoved { who, merkle_root }.into());
+            assert_eq!(Balances::reserved_balance(who), 0);
+        }
+        assert_eq!(TemplateModule::get_file_bytes(merkle_root.encode(), 0, 8), Ok(bytes[..8].to_vec()));
+        assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(4), merkle_root));
+        System::assert_last_event(Event::FileRemoved { who: 4, merkle_root }.into());
+
+        DuplicateUploads::set(DuplicatePolicy::Reject);
+        assert_ok!(upload_again(1));
+        assert_err!(upload_again(2), Error::<Test>::FileAlreadyExists);
+    });
+}
+
+#[test]
+fn only_owners_and_managers_set_the_metadata() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let merkle_root = upload(1, file_bytes(5, 10), None);
+        let set_metadata = |who: u64, name: &[u8], description: Option<Vec<u8>>| {
+            TemplateModule::set_file_metadata(RuntimeOrigin::signed(who), merkle_root, name.to_vec(), b"image/png".to_vec(), description)
+        };
+        let info = TemplateModule::get_file_info(merkle_root.encode()).unwrap();
+        assert!(info.name.is_empty() && info.content_type.is_empty() && info.description.is_none());
+
+        assert_err!(set_metadata(2, b"logo.png", None), Error::<Test>::NotOwner);
+        assert_ok!(set_metadata(1, b"logo.png", Some(b"The logo".to_vec())));
+        System::assert_last_event(Event::FileMetadataSet { who: 1, merkle_root }.into());
+        let info = TemplateModule::get_file_info(merkle_root.encode()).unwrap();
+        assert_eq!((info.name, info.content_type, info.description), (b"logo.png".to_vec(), b"image/png".to_vec(), Some(b"The logo".to_vec())));
+        assert_eq!((info.size, info.uploaded_at, info.expires_at), (310, 1, None));
+
+        // managers set it on behalf of the owner, replacing the previous metadata
+        assert_ok!(TemplateModule::add_manager(RuntimeOrigin::signed(1), merkle_root, 2));
+        assert_ok!(set_metadata(2, b"icon.png", None));
+        let info = TemplateModule::get_file_info(merkle_root.encode()).unwrap();
+        assert_eq!((info.name, info.description), (b"icon.png".to_vec(), None));
+
+        assert_err!(set_metadata(1, &[b'a'; 65], None), Error::<Test>::MetadataTooLong);
+        assert_err!(set_metadata(1, b"logo.png", Some(vec![b'a'; 257])), Error::<Test>::MetadataTooLong);
+        assert_err!(
+            TemplateModule::set_file_metadata(RuntimeOrigin::signed(1), H256::repeat_byte(1), Vec::new(), Vec::new(), None),
+            Error::<Test>::FileNotFound
+        );
+    });
+}
+
+#[test]
+fn files_are_listed_by_owner() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        Balances::make_free_balance_be(&2, 1_000_000);
+        Balances::make_free_balance_be(&3, 1_000_000);
+        let first = upload(1, file_bytes(5, 10), None);
+        let second = upload(1, file_bytes(3, 20), None);
+        assert_ok!(TemplateModule::upload_file(RuntimeOrigin::signed(2), file_bytes(5, 10), 6, TreeMode::Standard, None));
+        let owned = |who: u64| {
+            let mut files = TemplateModule::get_files_by_owner(who);
+            files.sort();
+            files
+        };
+        let mut expected = vec![
+            (first.encode(), 5, CHUNK_SIZE, TreeMode::Standard),
+            (second.encode(), 3, CHUNK_SIZE, TreeMode::Standard),
+        ];
+        expected.sort();
+        assert_eq!(owned(1), expected);
+        assert_eq!(owned(2), vec![(first.encode(), 5, CHUNK_SIZE, TreeMode::Standard)]);
+        assert!(owned(3).is_empty());
+
+        // the index follows the owners of the files
+        assert_ok!(TemplateModule::transfer_file(RuntimeOrigin::signed(1), second, 3));
+        assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(2), first));
+        assert_eq!(owned(1), vec![(first.encode(), 5, CHUNK_SIZE, TreeMode::Standard)]);
+        assert!(owned(2).is_empty());
+        assert_eq!(owned(3), vec![(second.encode(), 3, CHUNK_SIZE, TreeMode::Standard)]);
+        assert_eq!(FilesByOwner::<Test>::iter().count(), 2);
+    });
+}
+
+#[test]
+fn files_are_listed_in_pages() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        for index in 0..7u8 {
+            upload(1, vec![index + 1; 100], None);
+        }
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (files, next_cursor) = TemplateModule::get_files_paged(cursor, 3);
+            pages.push(files);
+            match next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => break,
+            }
+        }
+        // the pages follow each other, and hold every file once
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![3, 3, 1]);
+        assert_eq!(pages.concat(), TemplateModule::get_files());
+
+        // the cursor is the last file of the page, so a page ending with the last file has none
+        let (files, next_cursor) = TemplateModule::get_files_paged(None, 7);
+        assert_eq!((files.len(), next_cursor), (7, None));
+        let (files, next_cursor) = TemplateModule::get_files_paged(None, 6);
+        assert_eq!(next_cursor.map(|hash| hash.to_vec()), Some(files[5].0.clone()));
+        assert_eq!(TemplateModule::get_files_paged(next_cursor, 6).0, pages[2]);
+        assert_eq!(TemplateModule::get_files_paged(None, 0), (Vec::new(), None));
+    });
+}
+
+#[test]
+fn transfers_move_the_deposit_and_drop_the_managers() {
+    new_test_ext().execute_with(|| {
+        for who in 1..=3 {
+            Balances::make_free_balance_be(&who, 1_000_000);
+        }
+        let merkle_root = upload(1, file_bytes(5, 10), None);
+        let deposit = Balances::reserved_balance(1);
+        assert_ok!(TemplateModule::add_manager(RuntimeOrigin::signed(1), merkle_root, 3));
+
+        assert_err!(TemplateModule::transfer_file(RuntimeOrigin::signed(3), merkle_root, 2), Error::<Test>::NotOwner);
+        assert_err!(TemplateModule::transfer_file(RuntimeOrigin::signed(1), merkle_root, 1), Error::<Test>::AlreadyOwner);
+        assert_ok!(TemplateModule::transfer_file(RuntimeOrigin::signed(1), merkle_root, 2));
+        System::assert_last_event(Event::OwnershipTransferred { merkle_root, from: 1, to: 2 }.into());
+        // the deposit stays reserved, now by the new owner
+        assert_eq!((Balances::reserved_balance(1), Balances::free_balance(1)), (0, 1_000_000 - deposit));
+        assert_eq!(Balances::reserved_balance(2), deposit);
+        let stored_file = Files::<Test>::get(merkle_root).unwrap();
+        assert_eq!(stored_file.owners.into_inner(), vec![(2, deposit)]);
+        assert!(stored_file.managers.is_empty());
+        assert_err!(TemplateModule::remove_file(RuntimeOrigin::signed(3), merkle_root), Error::<Test>::NotOwner);
+    });
+}
+
+#[test]
+fn managers_act_on_behalf_of_the_owner_granting_them() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let merkle_root = upload(1, file_bytes(5, 10), None);
+        let add_manager = |who: u64, manager: u64| TemplateModule::add_manager(RuntimeOrigin::signed(who), merkle_root, manager);
+
+        assert_err!(add_manager(2, 3), Error::<Test>::NotOwner);
+        for manager in 2..=5 {
+            assert_ok!(add_manager(1, manager));
+        }
+        System::assert_last_event(Event::ManagerAdded { merkle_root, owner: 1, manager: 5 }.into());
+        assert_err!(add_manager(1, 2), Error::<Test>::AlreadyManager);
+        assert_err!(add_manager(1, 6), Error::<Test>::TooManyManagers);
+
+        assert_err!(TemplateModule::remove_manager(RuntimeOrigin::signed(2), merkle_root, 3), Error::<Test>::NotManager);
+        assert_ok!(TemplateModule::remove_manager(RuntimeOrigin::signed(1), merkle_root, 5));
+        System::assert_last_event(Event::ManagerRemoved { merkle_root, owner: 1, manager: 5 }.into());
+        assert_err!(TemplateModule::remove_manager(RuntimeOrigin::signed(1), merkle_root, 5), Error::<Test>::NotManager);
+        // managers cannot hand the file over, but remove it on behalf of the owner
+        assert_err!(TemplateModule::transfer_file(RuntimeOrigin::signed(2), merkle_root, 6), Error::<Test>::NotOwner);
+        assert_err!(TemplateModule::remove_file(RuntimeOrigin::signed(5), merkle_root), Error::<Test>::NotOwner);
+        assert_ok!(TemplateModule::remove_file(RuntimeOrigin::signed(2), merkle_root));
+        System::assert_last_event(Event::FileRemoved { who: 1, merkle_root }.into());
+        assert_eq!(Balances::reserved_balance(1), 0);
+    });
+}
+
+#[test]
+fn indexed_files_are_proven_out_of_their_bytes() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let bytes = file_bytes(5, 10);
+        // the bytes are indexed under the extrinsic, which only exists in a block
+        assert_err!(
+            TemplateModule::store_indexed(RuntimeOrigin::signed(1), 6, TreeMode::Standard, None, bytes.clone()),
+            Error::<Test>::BadContext
+        );
+        let merkle_root = store_indexed(1, bytes.clone());
+        assert!(Chunks::<Test>::iter_prefix(merkle_root).next().is_none());
+        assert!(TreeNodes::<Test>::iter_prefix(merkle_root).next().is_none());
+        assert_eq!(TemplateModule::get_file_info(merkle_root.encode()).unwrap().size, bytes.len() as u32);
+
+        let tree = tree(5, 10);
+        assert_eq!(TemplateModule::get_proof(merkle_root.encode(), 2), Err(FileError::Indexed));
+        assert_eq!(TemplateModule::get_indexed_proof(merkle_root.encode(), bytes.clone(), 2), Ok(chunk_proof(&tree, 2)));
+        assert_eq!(
+            TemplateModule::get_indexed_proof(merkle_root.encode(), bytes.clone(), 5),
+            Err(FileError::PositionOutOfRange { position: 5, pieces: 5 })
+        );
+        assert_eq!(TemplateModule::get_indexed_proofs(merkle_root.encode(), bytes.clone(), vec![4, 1]), Ok(tree.multi_proof(&[1, 4]).unwrap()));
+        assert_eq!(
+            TemplateModule::get_indexed_proof(merkle_root.encode(), bytes[1..].to_vec(), 2),
+            Err(FileError::BytesMismatch)
+        );
+    });
+}
+
+#[test]
+fn files_expire_unless_renewed() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        Balances::make_free_balance_be(&2, 1_000_000);
+        assert_err!(
+            TemplateModule::upload_file(RuntimeOrigin::signed(1), file_bytes(5, 10), 6, TreeMode::Standard, Some(0)),
+            Error::<Test>::InvalidRetention
+        );
+        let merkle_root = upload(1, file_bytes(5, 10), Some(5));
+        let forever = upload(1, file_bytes(3, 10), None);
+        assert_eq!(TemplateModule::get_file_info(merkle_root.encode()).unwrap().expires_at, Some(6));
+
+        assert_err!(TemplateModule::renew_file(RuntimeOrigin::signed(2), merkle_root, 10), Error::<Test>::NotOwner);
+        assert_err!(TemplateModule::renew_file(RuntimeOrigin::signed(1), merkle_root, 0), Error::<Test>::InvalidRetention);
+        assert_err!(TemplateModule::renew_file(RuntimeOrigin::signed(1), forever, 10), Error::<Test>::NotExpiring);
+        assert_ok!(TemplateModule::renew_file(RuntimeOrigin::signed(1), merkle_root, 10));
+        System::assert_last_event(Event::FileRenewed { who: 1, merkle_root, expires_at: 16 }.into());
+        // co-owners uploading the file for longer extend its retention too
+        assert_ok!(TemplateModule::upload_file(RuntimeOrigin::signed(2), file_bytes(5, 10), 6, TreeMode::Standard, Some(20)));
+        assert_eq!(TemplateModule::get_file_info(merkle_root.encode()).unwrap().expires_at, Some(21));
+
+        TemplateModule::on_initialize(6);
+        TemplateModule::on_initialize(16);
+        assert!(Files::<Test>::contains_key(merkle_root));
+        TemplateModule::on_initialize(21);
+        System::assert_last_event(Event::FileExpired { merkle_root }.into());
+        // the file is removed from all of its owners, who get their deposits back
+        assert!(!Files::<Test>::contains_key(merkle_root));
+        assert!(PendingDeletions::<Test>::contains_key(merkle_root));
+        assert_eq!(Balances::reserved_balance(1), Files::<Test>::get(forever).unwrap().owners[0].1);
+        assert_eq!(Balances::reserved_balance(2), 0);
+        assert!(TemplateModule::get_files_by_owner(2).is_empty());
+    });
+}
+
+#[test]
+fn uploaded_files_are_read_from_the_events_of_the_block() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        Balances::make_free_balance_be(&2, 1_000_000);
+        let merkle_root = upload(1, file_bytes(5, 10), None);
+        assert_ok!(TemplateModule::register_provider(RuntimeOrigin::signed(1)));
+        assert_ok!(TemplateModule::upload_file(RuntimeOrigin::signed(2), file_bytes(5, 10), 6, TreeMode::Standard, None));
+        let uploaded = |owner: u64, shared: bool| UploadedFile { merkle_root: merkle_root.encode(), owner, size: 310, pieces: 5, shared };
+        // only uploads are reported, in the order of the block
+        assert_eq!(TemplateModule::get_uploaded_files(), vec![uploaded(1, false), uploaded(2, true)]);
+
+        System::reset_events();
+        assert!(TemplateModule::get_uploaded_files().is_empty());
+    });
+}
+
+#[test]
+fn runtime_api_failures_are_typed() {
+    new_test_ext().execute_with(|| {
+        Balances::make_free_balance_be(&1, 1_000_000);
+        let merkle_root = upload(1, file_bytes(5, 10), None).encode();
+        let unknown = H256::repeat_byte(1).encode();
+        let malformed = merkle_root[1..].to_vec();
+
+        assert_eq!(TemplateModule::get_file_info(malformed.clone()), Err(FileError::InvalidMerkleRoot));
+        assert_eq!(TemplateModule::get_proof(malformed, 0), Err(FileError::InvalidMerkleRoot));
+        assert_eq!(TemplateModule::get_file_info(unknown.clone()), Err(FileError::FileNotFound));
+        assert_eq!(TemplateModule::get_proof(unknown.clone(), 0), Err(FileError::FileNotFound));
+        assert_eq!(TemplateModule::get_proofs(unknown.clone(), vec![0]), Err(FileError::FileNotFound));
+        assert_eq!(TemplateModule::get_file_bytes(unknown, 0, 8), Err(FileError::FileNotFound));
+
+        assert_eq!(TemplateModule::get_proof(merkle_root.clone(), 5), Err(FileError::PositionOutOfRange { position: 5, pieces: 5 }));
+        assert_eq!(
+            TemplateModule::get_proofs(merkle_root.clone(), vec![1, 7]),
+            Err(FileError::PositionOutOfRange { position: 7, pieces: 5 })
+        );
+        assert_eq!(TemplateModule::get_proofs(merkle_root.clone(), Vec::new()), Err(FileError::NoPositions));
+        assert_eq!(TemplateModule::get_file_bytes(merkle_root.clone(), 400, 8), Err(FileError::OffsetOutOfRange { offset: 400, size: 310 }));
+        assert_eq!(TemplateModule::get_indexed_proof(merkle_root, Vec::new(), 0), Err(FileError::NotIndexed));
+    });
+}
@@ -0,0 +1,324 @@
+//! Placeholder weights for pallet_template.
+//!
+//! Nothing in this file was measured. The numbers are round guesses, sized after the storage
+//! accesses of every call, so that the pallet can be configured and tested. Run the benchmarks of
+//! `benchmarking.rs` with `node-template benchmark pallet` and replace this file with their output
+//! before using the pallet on a live chain.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_template.
+pub trait WeightInfo {
+	fn upload_file(s: u32, p: u32, ) -> Weight;
+	fn remove_file() -> Weight;
+	fn begin_upload() -> Weight;
+	fn upload_part(b: u32, ) -> Weight;
+	fn finalize_upload(s: u32, p: u32, ) -> Weight;
+	fn abort_upload(p: u32, ) -> Weight;
+	fn delete_file_items(n: u32, ) -> Weight;
+	fn set_file_metadata() -> Weight;
+	fn transfer_file() -> Weight;
+	fn add_manager() -> Weight;
+	fn remove_manager() -> Weight;
+	fn store_indexed(s: u32, p: u32, ) -> Weight;
+	fn register_provider() -> Weight;
+	fn unregister_provider() -> Weight;
+	fn declare_file() -> Weight;
+	fn undeclare_file() -> Weight;
+	fn respond_challenge(s: u32, h: u32, ) -> Weight;
+	fn issue_challenge() -> Weight;
+	fn expire_challenge() -> Weight;
+	fn renew_file() -> Weight;
+	fn expire_file() -> Weight;
+	fn get_proof_full(p: u32, ) -> Weight;
+	fn get_proof_leaves(p: u32, ) -> Weight;
+	fn get_proof_root_only(p: u32, ) -> Weight;
+}
+
+/// Placeholder weights for pallet_template, see the module documentation.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn upload_file(s: u32, p: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(7_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(19 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+			.saturating_add(T::DbWeight::get().writes((3 as u64).saturating_mul(p as u64)))
+	}
+	fn remove_file() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn begin_upload() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn upload_part(b: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(b as u64))
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn finalize_upload(s: u32, p: u32, ) -> Weight {
+		Weight::from_ref_time(60_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(7_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(36 as u64))
+			.saturating_add(T::DbWeight::get().writes(22 as u64))
+			.saturating_add(T::DbWeight::get().writes((3 as u64).saturating_mul(p as u64)))
+	}
+	fn abort_upload(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(2 as u64))
+			.saturating_add(T::DbWeight::get().writes((1 as u64).saturating_mul(p as u64)))
+	}
+	fn delete_file_items(n: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000_000 as u64).saturating_mul(n as u64))
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+			.saturating_add(T::DbWeight::get().writes((1 as u64).saturating_mul(n as u64)))
+	}
+	fn set_file_metadata() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn transfer_file() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(5 as u64))
+	}
+	fn add_manager() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn remove_manager() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn store_indexed(s: u32, p: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(6_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(1_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(19 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn register_provider() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(4 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	fn unregister_provider() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	fn declare_file() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn undeclare_file() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn respond_challenge(s: u32, h: u32, ) -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(1_000_000 as u64).saturating_mul(h as u64))
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	fn issue_challenge() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(5 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn expire_challenge() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(4 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn renew_file() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(19 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	fn expire_file() -> Weight {
+		Weight::from_ref_time(200_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(17 as u64))
+			.saturating_add(T::DbWeight::get().writes(34 as u64))
+	}
+	fn get_proof_full(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(14 as u64))
+	}
+	fn get_proof_leaves(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().reads((1 as u64).saturating_mul(p as u64)))
+	}
+	fn get_proof_root_only(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().reads((1 as u64).saturating_mul(p as u64)))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn upload_file(s: u32, p: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(7_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(19 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes((3 as u64).saturating_mul(p as u64)))
+	}
+	fn remove_file() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn begin_upload() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn upload_part(b: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(b as u64))
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn finalize_upload(s: u32, p: u32, ) -> Weight {
+		Weight::from_ref_time(60_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(7_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(36 as u64))
+			.saturating_add(RocksDbWeight::get().writes(22 as u64))
+			.saturating_add(RocksDbWeight::get().writes((3 as u64).saturating_mul(p as u64)))
+	}
+	fn abort_upload(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes((1 as u64).saturating_mul(p as u64)))
+	}
+	fn delete_file_items(n: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000_000 as u64).saturating_mul(n as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes((1 as u64).saturating_mul(n as u64)))
+	}
+	fn set_file_metadata() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn transfer_file() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	fn add_manager() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn remove_manager() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn store_indexed(s: u32, p: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(6_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(1_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(19 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn register_provider() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	fn unregister_provider() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	fn declare_file() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn undeclare_file() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn respond_challenge(s: u32, h: u32, ) -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(s as u64))
+			.saturating_add(Weight::from_ref_time(1_000_000 as u64).saturating_mul(h as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	fn issue_challenge() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn expire_challenge() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn renew_file() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(19 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	fn expire_file() -> Weight {
+		Weight::from_ref_time(200_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(17 as u64))
+			.saturating_add(RocksDbWeight::get().writes(34 as u64))
+	}
+	fn get_proof_full(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(14 as u64))
+	}
+	fn get_proof_leaves(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(p as u64)))
+	}
+	fn get_proof_root_only(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(p as u64)))
+	}
+}
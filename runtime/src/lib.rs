@@ -105,10 +105,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 2,
 	state_version: 1,
 };
 
@@ -275,9 +275,51 @@ impl pallet_sudo::Config for Runtime {
 	type RuntimeCall = RuntimeCall;
 }
 
+parameter_types! {
+	pub const DuplicateUploads: pallet_template::DuplicatePolicy = pallet_template::DuplicatePolicy::Share;
+	pub const FileTreeStorage: pallet_template::TreeStorage = pallet_template::TreeStorage::Full;
+}
+
 /// Configure the pallet-template in pallets/template.
 impl pallet_template::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	/// Smallest chunk size is 64 bytes.
+	type MinChunkSizeExponent = ConstU8<6>;
+	/// Biggest chunk size is 1MB.
+	type MaxChunkSizeExponent = ConstU8<20>;
+	/// Merkle trees are built with `sha256`, as in the Bittorrent v2 protocol.
+	type FileHasher = pallet_template::Sha2_256;
+	/// Files bigger than a single extrinsic, which can take up to 75% of a 5MB block, are sent in
+	/// parts.
+	type MaxFileSize = ConstU32<{ 16 * 1024 * 1024 }>;
+	/// The biggest files must be divided in chunks of at least 4KB for their upload to fit in a
+	/// block.
+	type MaxPieces = ConstU32<4096>;
+	/// Uploading a stored file again makes the uploader one of its co-owners.
+	type DuplicatePolicy = DuplicateUploads;
+	/// Every merkle tree node is stored, so that proofs are served without hashing.
+	type TreeStorage = FileTreeStorage;
+	type MaxOwners = ConstU32<16>;
+	type MaxManagers = ConstU32<16>;
+	type MaxPartSize = ConstU32<{ 1024 * 1024 }>;
+	type MaxNameLength = ConstU32<256>;
+	type MaxContentTypeLength = ConstU32<128>;
+	type MaxDescriptionLength = ConstU32<1024>;
+	type UploadTimeout = ConstU32<{ 10 * MINUTES }>;
+	type MaxExpiriesPerBlock = ConstU32<64>;
+	/// Files expiring at a full block are delayed by up to 16 blocks.
+	type MaxExpiryDelay = ConstU32<16>;
+	type Currency = Balances;
+	type DepositBase = ConstU128<{ 100 * EXISTENTIAL_DEPOSIT }>;
+	type DepositPerByte = ConstU128<10>;
+	type Randomness = RandomnessCollectiveFlip;
+	type ProviderBond = ConstU128<{ 1000 * EXISTENTIAL_DEPOSIT }>;
+	type MaxProviders = ConstU32<256>;
+	type MaxProviderFiles = ConstU32<1024>;
+	/// A single storage provider is challenged every block, and has 2 minutes to answer.
+	type ChallengesPerBlock = ConstU32<1>;
+	type ChallengePeriod = ConstU32<{ 2 * MINUTES }>;
+	type WeightInfo = pallet_template::weights::SubstrateWeight<Runtime>;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -324,6 +366,9 @@ pub type UncheckedExtrinsic =
 	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, SignedExtra>;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<RuntimeCall, SignedExtra>;
+/// Storage migrations run on runtime upgrade, in order.
+pub type Migrations = (pallet_template::migrations::v1::MigrateToV1<Runtime>,);
+
 /// Executive: handles dispatch to the various modules.
 pub type Executive = frame_executive::Executive<
 	Runtime,
@@ -331,6 +376,7 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -479,14 +525,94 @@ impl_runtime_apis! {
 		}
 	}
 
-	impl pallet_template_runtime_api::TemplateApi<Block> for Runtime {
-		fn get_files() -> Vec<(Vec<u8>, u32)> {
+	impl pallet_template_runtime_api::TemplateApi<Block, AccountId, BlockNumber> for Runtime {
+		fn get_files() -> Vec<(Vec<u8>, u32, u32, pallet_template_runtime_api::TreeMode)> {
 			TemplateModule::get_files()
 		}
 
-		fn get_proof(merkle_root: Vec<u8>, position: u32) -> Option<(Vec<u8>, Vec<Vec<u8>>)> {
+		fn get_files_paged(
+			start_after: Option<pallet_template_runtime_api::Hash>,
+			limit: u32,
+		) -> (Vec<(Vec<u8>, u32, u32, pallet_template_runtime_api::TreeMode)>, Option<pallet_template_runtime_api::Hash>) {
+			TemplateModule::get_files_paged(start_after, limit)
+		}
+
+		fn get_files_by_owner(owner: AccountId) -> Vec<(Vec<u8>, u32, u32, pallet_template_runtime_api::TreeMode)> {
+			TemplateModule::get_files_by_owner(owner)
+		}
+
+		fn get_file_info(
+			merkle_root: Vec<u8>,
+		) -> Result<pallet_template_runtime_api::FileInfo<BlockNumber>, pallet_template_runtime_api::FileError> {
+			TemplateModule::get_file_info(merkle_root)
+		}
+
+		fn get_proof(
+			merkle_root: Vec<u8>,
+			position: u32,
+		) -> Result<pallet_template_runtime_api::MerkleProof, pallet_template_runtime_api::FileError> {
 			TemplateModule::get_proof(merkle_root, position)
 		}
+
+		fn get_indexed_proof(
+			merkle_root: Vec<u8>,
+			file_bytes: Vec<u8>,
+			position: u32,
+		) -> Result<pallet_template_runtime_api::MerkleProof, pallet_template_runtime_api::FileError> {
+			TemplateModule::get_indexed_proof(merkle_root, file_bytes, position)
+		}
+
+		fn get_proofs(
+			merkle_root: Vec<u8>,
+			positions: Vec<u32>,
+		) -> Result<pallet_template_runtime_api::MultiProof, pallet_template_runtime_api::FileError> {
+			TemplateModule::get_proofs(merkle_root, positions)
+		}
+
+		fn get_indexed_proofs(
+			merkle_root: Vec<u8>,
+			file_bytes: Vec<u8>,
+			positions: Vec<u32>,
+		) -> Result<pallet_template_runtime_api::MultiProof, pallet_template_runtime_api::FileError> {
+			TemplateModule::get_indexed_proofs(merkle_root, file_bytes, positions)
+		}
+
+		fn get_file_bytes(
+			merkle_root: Vec<u8>,
+			offset: u32,
+			length: u32,
+		) -> Result<Vec<u8>, pallet_template_runtime_api::FileError> {
+			TemplateModule::get_file_bytes(merkle_root, offset, length)
+		}
+
+		fn get_indexed_file_bytes(
+			merkle_root: Vec<u8>,
+			file_bytes: Vec<u8>,
+			offset: u32,
+			length: u32,
+		) -> Result<Vec<u8>, pallet_template_runtime_api::FileError> {
+			TemplateModule::get_indexed_file_bytes(merkle_root, file_bytes, offset, length)
+		}
+
+		fn get_uploaded_files() -> Vec<pallet_template_runtime_api::UploadedFile<AccountId>> {
+			TemplateModule::get_uploaded_files()
+		}
+
+		fn get_challenges(provider: AccountId) -> Vec<(u64, Vec<u8>, u32)> {
+			TemplateModule::get_challenges(provider)
+		}
+
+		fn verify_chunk(
+			merkle_root: Vec<u8>,
+			position: u32,
+			content: Vec<u8>,
+			proof: Vec<pallet_template_runtime_api::Hash>,
+			pieces: u32,
+			chunk_size: u32,
+			mode: pallet_template_runtime_api::TreeMode,
+		) -> bool {
+			TemplateModule::verify_chunk(merkle_root, position, content, proof, pieces, chunk_size, mode)
+		}
 	}
 
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentCallApi<Block, Balance, RuntimeCall>
@@ -573,7 +699,7 @@ impl_runtime_apis! {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use frame_support::traits::WhitelistedStorageKeys;
+	use frame_support::{dispatch::DispatchClass, traits::WhitelistedStorageKeys};
 	use sp_core::hexdisplay::HexDisplay;
 	use std::collections::HashSet;
 
@@ -605,4 +731,20 @@ mod tests {
 			whitelist.contains("26aa394eea5630e07c48ae0c9558cef780d41e5e16056765bc8461851072c9d7")
 		);
 	}
+
+	#[test]
+	fn heaviest_uploads_fit_in_a_block() {
+		use pallet_template::WeightInfo;
+		type Weights = <Runtime as pallet_template::Config>::WeightInfo;
+
+		let max_extrinsic = BlockWeights::get()
+			.get(DispatchClass::Normal)
+			.max_extrinsic
+			.expect("normal extrinsics are bounded; qed");
+		let size = <Runtime as pallet_template::Config>::MaxFileSize::get();
+		let pieces = <Runtime as pallet_template::Config>::MaxPieces::get();
+		assert!(Weights::upload_file(size, pieces).all_lte(max_extrinsic));
+		assert!(Weights::store_indexed(size, pieces).all_lte(max_extrinsic));
+		assert!(Weights::finalize_upload(size, pieces).all_lte(max_extrinsic));
+	}
 }